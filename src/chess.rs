@@ -1,154 +1,467 @@
 mod piececolor;
 mod piecename;
 mod movevalidator;
+mod castlingrights;
+mod gamestatus;
+mod positionkey;
+mod fen;
+mod chessmove;
+mod movegen;
+mod mailbox;
+mod perft;
+mod san;
+mod pgn;
 
 pub use crate::chess::piececolor::PieceColor;
 pub use crate::chess::piecename::PieceName;
 pub use crate::chess::movevalidator::MoveValidator;
+pub use crate::chess::castlingrights::CastlingRights;
+pub use crate::chess::gamestatus::{GameStatus, GameResult, DrawReason};
+pub use crate::chess::positionkey::PositionKey;
+pub use crate::chess::fen::FenError;
+pub use crate::chess::pgn::{PgnGame, PgnError};
+pub use crate::chess::chessmove::{Move, MoveKind};
+use crate::chess::chessmove::MoveRecord;
 
 type Piece  = u8;
 type Square = usize;
 
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
 #[derive(Clone)]
 pub struct Chess {
     board: [Option<Piece>; 64],
     turn: PieceColor,
     white_king_square: Square,
     black_king_square: Square,
-    white_can_castle: bool,
-    black_can_castle: bool,
-    move_history: Vec<(Square, Square)>,
+    castling_rights: CastlingRights,
+    en_passant_square: Option<Square>,
+    move_history: Vec<MoveRecord>,
+    // Half moves since the last capture or pawn move, for the fifty and seventy-five move rules
+    halfmove_clock: u32,
+    // Starts at 1 and goes up after every black move
+    fullmove_number: u32,
+    // Every position reached so far, including the current one, for the repetition rules
+    position_history: Vec<PositionKey>,
+    // Moves taken back with takeback(), most recent last, replayed by redo()
+    redo_stack: Vec<Move>,
+    status: GameStatus,
 }
 
 impl Chess {
-    pub fn new(fen: &str) -> Self {
-        let mut chess = Chess {
+    pub fn new(fen: &str) -> Result<Self, FenError> {
+        let mut chess = Chess::empty();
+        chess.load_fen(fen)?;
+        Ok(chess)
+    }
+
+    fn empty() -> Self {
+        Chess {
             board: [None; 64],
             turn: PieceColor::WHITE,
             white_king_square: 64,
             black_king_square: 64,
-            white_can_castle: false,
-            black_can_castle: false,
+            castling_rights: CastlingRights::none(),
+            en_passant_square: None,
             move_history: Vec::new(),
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            position_history: Vec::new(),
+            redo_stack: Vec::new(),
+            status: GameStatus::Ongoing,
+        }
+    }
+
+    pub fn update_king_square(&mut self, square: Square, color: PieceColor) {
+        assert!(square < 64);
+        match color {
+            PieceColor::BLACK => self.black_king_square = square,
+            PieceColor::WHITE => self.white_king_square = square,
         };
+    }
 
-        chess.load_fen(fen);
+    // Returns whether the move was made.
+    // Moves that promote a pawn must name the piece to promote to, any other move must not.
+    // Once the game is over no more moves are accepted.
+    pub fn attempt_move(&mut self, src: Square, dst: Square, promotion: Option<PieceName>) -> bool {
+        assert!(src < 64);
+        assert!(dst < 64);
+
+        if self.status.is_over() { return false; }
+
+        // A pawn reaching the last rank comes up once for every piece it can promote to, every other move exactly once.
+        let legal_move = self.legal_moves_from(src)
+            .into_iter()
+            .find(|mv| mv.dst == dst && mv.promotion == promotion);
+
+        let Some(mv) = legal_move else { return false; };
+
+        log::info!("Played {}", self.move_to_san(&mv));
+        self.make_move(mv);
+        self.update_status();
 
-        assert!(chess.white_king_square != 64);
-        assert!(chess.black_king_square != 64);
+        // Playing the move that would be redone keeps the rest of the redo branch, any other move starts a new one
+        match self.redo_stack.last() == Some(&mv) {
+            true  => { self.redo_stack.pop(); },
+            false => self.redo_stack.clear(),
+        };
+
+        true
+    }
 
-        chess
+    pub fn get_status(&self) -> GameStatus {
+        self.status
     }
 
-    pub fn load_fen(&mut self, fen: &str) {
-        assert!( fen.is_ascii() );
+    // Decides whether the game has ended, either by checkmate, stalemate or one of the automatic draw rules.
+    // Checkmate takes precedence over the automatic draws.
+    // attempt_move() does this after every move, anyone playing moves with make_move() calls it once they are done.
+    pub fn update_status(&mut self) {
+        if !self.has_legal_move() {
+            self.status = match self.is_in_check() {
+                true  => GameStatus::Checkmate(self.turn.opposite()),
+                false => GameStatus::Stalemate,
+            };
+            return;
+        }
 
-        self.clear_board();
+        self.status = if self.count_repetitions() >= 5 {
+            GameStatus::Draw(DrawReason::FivefoldRepetition)
+        } else if self.halfmove_clock >= 150 {
+            GameStatus::Draw(DrawReason::SeventyFiveMoveRule)
+        } else if self.has_insufficient_material() {
+            GameStatus::Draw(DrawReason::InsufficientMaterial)
+        } else {
+            GameStatus::Ongoing
+        };
+    }
 
-        let mut square: Square = 0;
-        for ch in fen.chars() {
+    // Returns the draw the side to move may claim right now, if any.
+    pub fn get_claimable_draw(&self) -> Option<DrawReason> {
+        if self.status.is_over() { return None; }
 
-            // Skip '/'
-            if ch == '/' { continue; }
+        if self.count_repetitions() >= 3 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
 
-            assert!( ch.is_ascii_alphanumeric() );
+    // Ends the game in a draw if one can be claimed. Returns whether the claim was accepted.
+    pub fn claim_draw(&mut self) -> bool {
+        match self.get_claimable_draw() {
+            Some(reason) => {
+                self.status = GameStatus::Draw(reason);
+                true
+            },
+            None => false,
+        }
+    }
 
-            // For digits, increment board_ptr by digit, continue
-            if let Some(digit) = ch.to_digit(/* Radix */ 10) {
-                assert!(digit > 0 && digit <= 8);
-                square += digit as Square;
-                continue;
-            }
+    // Whether the current position has occurred before. Engines treat this as a draw, since repeating is always possible.
+    pub fn is_repetition(&self) -> bool {
+        self.count_repetitions() >= 2
+    }
 
-            // For chars setup the corresponding black or white piece at that square
-            assert!( ch.is_ascii_alphabetic() );
+    // How many times the current position has occurred, counting the current occurrence.
+    fn count_repetitions(&self) -> usize {
+        let current = self.position_history.last().unwrap();
+
+        // A capture or pawn move can never be undone, so only positions since the last one can repeat.
+        self.position_history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize + 1)
+            .filter(|&key| key == current)
+            .count()
+    }
 
-            let is_black: bool = ch.is_ascii_lowercase();
+    pub fn position_key(&self) -> PositionKey {
+        // The en passant target is only part of the position when a capture on it is actually possible.
+        let en_passant_square = self.en_passant_square.filter(|&square| self.can_capture_en_passant(square));
 
-            let color = match is_black {
-                true  => PieceColor::BLACK as u8,
-                false => PieceColor::WHITE as u8,
-            };
+        PositionKey {
+            board: self.board,
+            turn: self.turn,
+            castling_rights: self.castling_rights,
+            en_passant_square,
+        }
+    }
 
-            let name = match ch.to_ascii_lowercase() {
-                'p' => PieceName::PAWN   as u8,
-                'r' => PieceName::ROOK   as u8,
-                'n' => PieceName::KNIGHT as u8,
-                'b' => PieceName::BISHOP as u8,
-                'q' => PieceName::QUEEN  as u8,
-                'k' => PieceName::KING   as u8,
-                 _  => panic!("Invalid character in FEN string."),
-            };
+    // Only a pawn of the side to move standing right beside the pawn that just moved two squares can capture it,
+    // and only if doing so does not leave its own king in check.
+    fn can_capture_en_passant(&self, en_passant_square: Square) -> bool {
+        let behind = match self.turn {
+            PieceColor::WHITE => mailbox::DOWN,
+            PieceColor::BLACK => mailbox::UP,
+        };
+        let Some(victim_square) = mailbox::offset_square(en_passant_square, behind) else { return false; };
+        let own_pawn = PieceName::PAWN as u8 | self.turn as u8;
 
-            let piece = name | color;
-            self.set_piece_at_square(square, piece);
+        [-1, 1].iter()
+            .filter_map(|&offset| mailbox::offset_square(victim_square, offset))
+            .filter(|&square| self.get_piece_at_square(square) == Some(own_pawn))
+            .any(|square| !self.leaves_king_in_check(&self.create_move(square, en_passant_square, None)))
+    }
 
-            if Chess::get_name_for_piece(piece) == PieceName::KING {
-                self.update_king_square(square, Chess::get_color_for_piece(piece));
+    // King vs king, king and a single minor piece vs king, or only bishops that all stand on the same square color.
+    // None of these can ever lead to checkmate.
+    pub fn has_insufficient_material(&self) -> bool {
+        let mut minor_pieces = 0;
+        let mut bishop_square_colors = Vec::new();
+
+        for square in 0..=63 {
+            if let Some(piece) = self.get_piece_at_square(square) {
+                match Chess::get_name_for_piece(piece) {
+                    PieceName::KING   => {},
+                    PieceName::KNIGHT => minor_pieces += 1,
+                    PieceName::BISHOP => {
+                        minor_pieces += 1;
+                        bishop_square_colors.push((square + square / 8) % 2);
+                    },
+                    PieceName::PAWN | PieceName::ROOK | PieceName::QUEEN => return false,
+                }
             }
+        }
+
+        if minor_pieces <= 1 { return true; }
 
-            square += 1;
+        // Only bishops left, all on squares of one color
+        bishop_square_colors.len() == minor_pieces && bishop_square_colors.windows(2).all(|pair| pair[0] == pair[1])
+    }
 
+    // Whether color still has the pieces to checkmate with: a pawn, rook or queen, or at least two minor pieces.
+    // A lone king, or a king with a single bishop or knight, is treated as unable to ever mate.
+    pub fn has_mating_material(&self, color: PieceColor) -> bool {
+        let mut minor_pieces = 0;
+
+        for square in 0..=63 {
+            if let Some(piece) = self.get_piece_at_square(square).filter(|&piece| Chess::get_color_for_piece(piece) == color) {
+                match Chess::get_name_for_piece(piece) {
+                    PieceName::KING => {},
+                    PieceName::KNIGHT | PieceName::BISHOP => minor_pieces += 1,
+                    PieceName::PAWN | PieceName::ROOK | PieceName::QUEEN => return true,
+                }
+            }
         }
 
+        minor_pieces >= 2
     }
 
-    pub fn update_king_square(&mut self, square: Square, color: PieceColor) {
-        assert!(square < 64);
-        match color {
-            PieceColor::BLACK => self.black_king_square = square,
-            PieceColor::WHITE => self.white_king_square = square,
+    // Ends the game because color ran out of time. Their opponent wins, unless they have nothing left to mate with.
+    pub fn flag_fall(&mut self, color: PieceColor) {
+        if self.status.is_over() { return; }
+
+        let opponent = color.opposite();
+        self.status = match self.has_mating_material(opponent) {
+            true  => GameStatus::TimeForfeit(opponent),
+            false => GameStatus::Draw(DrawReason::TimeoutVsInsufficientMaterial),
         };
     }
 
-    pub fn clear_board(&mut self) {
-        self.board = [None; 64];
-        self.white_king_square = 64;
-        self.black_king_square = 64;
+    // Is the king of the side to move attacked?
+    pub fn is_in_check(&self) -> bool {
+        MoveValidator::is_king_in_check(self.get_king_square(self.turn), self)
     }
 
-    pub fn attempt_move(&mut self, src: Square, dst: Square) {
-        assert!(src < 64);
-        assert!(dst < 64);
+    fn has_legal_move(&self) -> bool {
+        !self.legal_moves().is_empty()
+    }
 
-        if let Some(piece) = self.get_piece_at_square(src) {
-
-            if self.is_turn_for_piece(piece) {
-                if MoveValidator::validate_move(src, dst, &self) == true {
-
-                    // Make move if it is validated (Does not process checks)
-                    self.move_piece(src, dst);
-
-                    // Check for checks on same-color king
-                    let king_square = {
-                        let piece_color = Self::get_color_for_piece(piece);
-                        self.get_king_square(piece_color)
-                    };
-
-                    if MoveValidator::is_king_in_check(king_square, &self) {
-                        self.undo_last_move();
-                    } else {
-                        // Update position of king if it has moved.
-                        if PieceName::KING == Chess::get_name_for_piece(piece) {
-                            let king_color = Chess::get_color_for_piece(piece);
-                            self.update_king_square(dst, king_color);
-                            
-                            match king_color {
-                                PieceColor::WHITE => { self.white_can_castle = false; },
-                                PieceColor::BLACK => { self.black_can_castle = false; },
-                            };
-                        }
-                    }
-                    
-                }
-            }
+    // Whether moving src to dst is a legal pawn move onto the last rank, so the UI knows to ask for a promotion piece.
+    pub fn is_promotion_move(&self, src: Square, dst: Square) -> bool {
+        self.legal_moves_from(src)
+            .iter()
+            .any(|mv| mv.dst == dst && mv.promotion.is_some())
+    }
+
+    // Makes the move along with all of its side effects: the castling rook, the pawn taken en passant,
+    // the promoted piece, king squares, castling rights, the en passant target and the move clocks.
+    // Does not check legality, so mv should come from legal_moves().
+    // Does not decide whether the game is over either, that is left to attempt_move().
+    pub fn make_move(&mut self, mv: Move) {
+        let color = self.turn;
+
+        let captured_square = match mv.kind {
+            MoveKind::EnPassant => Self::en_passant_victim_square(&mv),
+            _                   => mv.dst,
+        };
+
+        let record = MoveRecord {
+            mv,
+            captured: self.get_piece_at_square(captured_square).map(|piece| (captured_square, piece)),
+            castling_rights: self.castling_rights,
+            en_passant_square: self.en_passant_square,
+            halfmove_clock: self.halfmove_clock,
+            status: self.status,
+        };
+
+        self.move_piece(mv.src, mv.dst);
+
+        match mv.kind {
+            MoveKind::EnPassant => {
+                // The captured pawn sits next to the source square, on the destination's file.
+                self.remove_piece_at_square(Self::en_passant_victim_square(&mv));
+            },
+            MoveKind::Castle => {
+                // The rook comes along with the king.
+                let (rook_src, rook_dst) = CastlingRights::rook_squares_for_castle(mv.src, mv.dst);
+                let rook = self.get_piece_at_square(rook_src).unwrap();
+                self.set_piece_at_square(rook_dst, rook);
+                self.remove_piece_at_square(rook_src);
+            },
+            MoveKind::Normal | MoveKind::DoublePawnPush => {},
+        };
+
+        if let Some(promoted_name) = mv.promotion {
+            self.set_piece_at_square(mv.dst, promoted_name as u8 | color as u8);
+        }
+
+        // Update position of king if it has moved.
+        if mv.piece == PieceName::KING {
+            self.update_king_square(mv.dst, color);
         }
+
+        // Moving a king or rook off its home square, or capturing a rook on it, loses that castling right.
+        self.castling_rights.revoke_for_square(mv.src);
+        self.castling_rights.revoke_for_square(mv.dst);
+
+        // After a double pawn push the skipped square can be captured on en passant, but only on the very next move.
+        self.en_passant_square = match mv.kind {
+            MoveKind::DoublePawnPush => Some((mv.src + mv.dst) / 2),
+            _                        => None,
+        };
+
+        self.halfmove_clock = match mv.piece == PieceName::PAWN || mv.is_capture() {
+            true  => 0,
+            false => self.halfmove_clock + 1,
+        };
+
+        if color == PieceColor::BLACK {
+            self.fullmove_number += 1;
+        }
+
+        self.move_history.push(record);
+        self.position_history.push(self.position_key());
+    }
+
+    // Takes back the last move made, restoring the position exactly as it was before, captured pieces included.
+    // Returns the move that was taken back, or None if there are no moves to take back.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let record = self.move_history.pop()?;
+        self.position_history.pop();
+
+        let mv = record.mv;
+        self.next_turn();
+        let color = self.turn;
+
+        // Put the piece back where it came from, turning a promoted piece back into a pawn.
+        self.set_piece_at_square(mv.src, mv.piece as u8 | color as u8);
+        self.remove_piece_at_square(mv.dst);
+
+        if mv.kind == MoveKind::Castle {
+            let (rook_src, rook_dst) = CastlingRights::rook_squares_for_castle(mv.src, mv.dst);
+            let rook = self.get_piece_at_square(rook_dst).unwrap();
+            self.set_piece_at_square(rook_src, rook);
+            self.remove_piece_at_square(rook_dst);
+        }
+
+        if let Some((square, piece)) = record.captured {
+            self.set_piece_at_square(square, piece);
+        }
+
+        if mv.piece == PieceName::KING {
+            self.update_king_square(mv.src, color);
+        }
+
+        self.castling_rights   = record.castling_rights;
+        self.en_passant_square = record.en_passant_square;
+        self.halfmove_clock    = record.halfmove_clock;
+        self.status            = record.status;
+
+        if color == PieceColor::BLACK {
+            self.fullmove_number -= 1;
+        }
+
+        Some(mv)
+    }
+
+    // Takes back the last move so that redo() can play it again. Returns the move taken back.
+    pub fn takeback(&mut self) -> Option<Move> {
+        let mv = self.unmake_move()?;
+        self.redo_stack.push(mv);
+        Some(mv)
+    }
+
+    // Plays the last move taken back by takeback() again. Returns the move replayed.
+    // Nothing is replayed once the game is over, e.g. after a draw was claimed in the position that was taken back to.
+    pub fn redo(&mut self) -> Option<Move> {
+        if self.status.is_over() { return None; }
+
+        let mv = self.redo_stack.pop()?;
+        self.make_move(mv);
+        self.update_status();
+        Some(mv)
+    }
+
+    fn en_passant_victim_square(mv: &Move) -> Square {
+        mv.src - mv.src % 8 + mv.dst % 8
     }
 
     pub fn get_turn(&self) -> PieceColor {
         self.turn
     }
 
+    // Half moves since the last capture or pawn move
+    pub fn get_halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn get_fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    // The position after the first ply half moves of the game, 0 being the position the game started from.
+    pub fn position_at_ply(&self, ply: usize) -> Chess {
+        let mut position = self.clone();
+        while position.move_history.len() > ply {
+            position.unmake_move();
+        }
+        position
+    }
+
+    pub fn get_last_move(&self) -> Option<Move> {
+        self.move_history.last().map(|record| record.mv)
+    }
+
+    // Every move played so far, oldest first.
+    pub fn get_played_moves(&self) -> Vec<Move> {
+        self.move_history.iter().map(|record| record.mv).collect()
+    }
+
+    // Converts a square name such as "e4" to its board index.
+    pub fn square_from_name(name: &str) -> Option<Square> {
+        let bytes = name.as_bytes();
+        if bytes.len() != 2 { return None; }
+
+        let file = bytes[0].wrapping_sub(b'a') as Square;
+        let rank = bytes[1].wrapping_sub(b'1') as Square;
+        if file >= 8 || rank >= 8 { return None; }
+
+        // Index 0 is a8, so ranks count downwards from the top of the board
+        Some((7 - rank) * 8 + file)
+    }
+
+    // Converts a board index to its square name, for example 36 becomes "e4".
+    pub fn square_name(square: Square) -> String {
+        assert!(square < 64);
+        let file = (b'a' + (square % 8) as u8) as char;
+        let rank = (b'8' - (square / 8) as u8) as char;
+        format!("{file}{rank}")
+    }
+
     fn next_turn(&mut self) {
         self.turn = match self.turn {
             PieceColor::BLACK => PieceColor::WHITE,
@@ -156,12 +469,6 @@ impl Chess {
         };
     }
 
-    pub fn get_resulting_square_for_move(src: Square, mov: i32) -> Square {
-        let dst = src as i32 + mov;
-        assert!(dst < 64);
-        dst as Square
-    }
-
     pub fn is_square_empty(&self, square: Square) -> bool {
         self.board[square].is_none()
     }
@@ -210,19 +517,7 @@ impl Chess {
         self.set_piece_at_square(dst, piece);
         self.remove_piece_at_square(src);
 
-        self.move_history.push( (src, dst) );
-
-        self.next_turn();
-    }
-
-    // Used when a move has put a color's own king in check
-    fn undo_last_move(&mut self) {
-        assert!(!self.move_history.is_empty());
-
-        let (src, dst) = self.move_history.pop().unwrap();
         self.next_turn();
-        self.move_piece(dst, src);
-
     }
 
     pub fn get_king_square(&self, color: PieceColor) -> Square {
@@ -232,4 +527,250 @@ impl Chess {
         }
     }
 
-}
\ No newline at end of file
+}
+
+// Plays the given SAN moves in order, failing the test on the first one that can't be played
+#[cfg(test)]
+pub(crate) fn play_san(chess: &mut Chess, moves: &[&str]) {
+    for san in moves {
+        let mv = chess.parse_san(san).unwrap();
+        assert!(chess.attempt_move(mv.src, mv.dst, mv.promotion));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::{play_san, Chess, DrawReason, GameStatus, PieceColor, STARTING_FEN};
+
+    #[test]
+    fn takeback_restores_captures_and_redo_replays() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        play_san(&mut chess, &["e4", "d5", "exd5", "Qxd5"]);
+        let fen_after_capture = chess.to_fen();
+
+        chess.takeback();
+        chess.takeback();
+        assert_eq!(chess.to_fen(), "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2");
+
+        chess.redo();
+        chess.redo();
+        assert_eq!(chess.to_fen(), fen_after_capture);
+        assert_eq!(chess.redo(), None);
+    }
+
+    #[test]
+    fn takeback_undoes_checkmate() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        play_san(&mut chess, &["f3", "e5", "g4", "Qh4#"]);
+        assert_eq!(chess.get_status(), GameStatus::Checkmate(PieceColor::BLACK));
+
+        chess.takeback();
+        assert_eq!(chess.get_status(), GameStatus::Ongoing);
+
+        chess.redo();
+        assert_eq!(chess.get_status(), GameStatus::Checkmate(PieceColor::BLACK));
+    }
+
+    #[test]
+    fn flag_fall_is_a_draw_when_opponent_cannot_mate() {
+        let mut chess = Chess::new("4k3/8/8/8/8/8/4P3/2N1K3 w - - 0 40").unwrap();
+        chess.flag_fall(PieceColor::WHITE);
+        assert_eq!(chess.get_status(), GameStatus::Draw(DrawReason::TimeoutVsInsufficientMaterial));
+
+        let mut chess = Chess::new("4k3/8/8/8/8/8/4P3/2N1K3 w - - 0 40").unwrap();
+        chess.flag_fall(PieceColor::BLACK);
+        assert_eq!(chess.get_status(), GameStatus::TimeForfeit(PieceColor::WHITE));
+        assert!(!chess.attempt_move(52, 44, None));
+    }
+
+    #[test]
+    fn position_at_ply_leaves_game_untouched() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        play_san(&mut chess, &["e4", "c5", "Nf3"]);
+        let fen = chess.to_fen();
+
+        assert_eq!(chess.position_at_ply(0).to_fen(), STARTING_FEN);
+        assert_eq!(chess.position_at_ply(2).to_fen(), "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2");
+        assert_eq!(chess.position_at_ply(3).to_fen(), fen);
+        assert_eq!(chess.to_fen(), fen);
+    }
+
+    #[test]
+    fn new_move_discards_redo_branch() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        play_san(&mut chess, &["e4", "e5", "Nf3"]);
+
+        // Replaying the same move keeps what is left of the branch
+        chess.takeback();
+        chess.takeback();
+        play_san(&mut chess, &["e5"]);
+        assert!(chess.redo().is_some());
+
+        // A different move drops it
+        chess.takeback();
+        play_san(&mut chess, &["d4"]);
+        assert_eq!(chess.redo(), None);
+        assert_eq!(chess.get_played_moves().len(), 3);
+    }
+
+    #[test]
+    fn king_move_loses_both_castling_rights() {
+        let mut chess = Chess::new("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        play_san(&mut chess, &["Kf1"]);
+
+        let rights = chess.castling_rights;
+        assert!(!rights.kingside(PieceColor::WHITE) && !rights.queenside(PieceColor::WHITE));
+        assert!(rights.kingside(PieceColor::BLACK) && rights.queenside(PieceColor::BLACK));
+
+        // Coming back home does not bring them back
+        play_san(&mut chess, &["Kd8", "Ke1"]);
+        let rights = chess.castling_rights;
+        assert!(!rights.kingside(PieceColor::WHITE) && !rights.queenside(PieceColor::WHITE));
+        assert!(!rights.kingside(PieceColor::BLACK) && !rights.queenside(PieceColor::BLACK));
+    }
+
+    #[test]
+    fn rook_move_loses_only_its_side() {
+        let mut chess = Chess::new("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        play_san(&mut chess, &["Rh2", "Ra7"]);
+
+        let rights = chess.castling_rights;
+        assert!(!rights.kingside(PieceColor::WHITE) && rights.queenside(PieceColor::WHITE));
+        assert!(rights.kingside(PieceColor::BLACK) && !rights.queenside(PieceColor::BLACK));
+    }
+
+    #[test]
+    fn rook_captured_at_home_loses_its_side() {
+        let mut chess = Chess::new("r3k2r/8/8/8/8/8/6B1/R3K2R w KQkq - 0 1").unwrap();
+        play_san(&mut chess, &["Bxa8"]);
+
+        let rights = chess.castling_rights;
+        assert!(rights.kingside(PieceColor::WHITE) && rights.queenside(PieceColor::WHITE));
+        assert!(rights.kingside(PieceColor::BLACK) && !rights.queenside(PieceColor::BLACK));
+    }
+
+    #[test]
+    fn fools_mate_is_checkmate() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        play_san(&mut chess, &["f3", "e5", "g4"]);
+        assert_eq!(chess.get_status(), GameStatus::Ongoing);
+
+        play_san(&mut chess, &["Qh4#"]);
+        assert_eq!(chess.get_status(), GameStatus::Checkmate(PieceColor::BLACK));
+        assert!(!chess.attempt_move(52, 44, None));
+    }
+
+    #[test]
+    fn no_legal_move_without_check_is_stalemate() {
+        let mut chess = Chess::new("k7/8/8/8/8/8/8/1Q5K w - - 0 1").unwrap();
+        play_san(&mut chess, &["Qb6"]);
+        assert_eq!(chess.get_status(), GameStatus::Stalemate);
+        assert!(!chess.attempt_move(0, 1, None));
+    }
+
+    fn status_after(fen: &str, moves: &[&str]) -> GameStatus {
+        let mut chess = Chess::new(fen).unwrap();
+        play_san(&mut chess, moves);
+        chess.get_status()
+    }
+
+    #[test]
+    fn threefold_repetition_can_be_claimed() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        play_san(&mut chess, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert_eq!(chess.get_claimable_draw(), None);
+        assert!(!chess.claim_draw());
+
+        play_san(&mut chess, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert_eq!(chess.get_status(), GameStatus::Ongoing);
+        assert_eq!(chess.get_claimable_draw(), Some(DrawReason::ThreefoldRepetition));
+
+        assert!(chess.claim_draw());
+        assert_eq!(chess.get_status(), GameStatus::Draw(DrawReason::ThreefoldRepetition));
+    }
+
+    #[test]
+    fn fivefold_repetition_ends_the_game() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        for _ in 0..3 {
+            play_san(&mut chess, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+        }
+        play_san(&mut chess, &["Nf3", "Nf6", "Ng1"]);
+        assert_eq!(chess.get_status(), GameStatus::Ongoing);
+
+        play_san(&mut chess, &["Ng8"]);
+        assert_eq!(chess.get_status(), GameStatus::Draw(DrawReason::FivefoldRepetition));
+        assert!(!chess.attempt_move(62, 45, None));
+    }
+
+    #[test]
+    fn unusable_en_passant_square_does_not_break_repetition() {
+        // After e4 no black pawn can take on e3, so the position repeats the one after the knights come back
+        let mut chess = Chess::new("4k3/8/8/8/8/8/4P3/4K1N1 w - - 0 1").unwrap();
+        play_san(&mut chess, &["e4", "Kd7", "Nf3", "Ke8", "Ng1", "Kd7", "Nf3", "Ke8", "Ng1"]);
+        assert_eq!(chess.get_claimable_draw(), Some(DrawReason::ThreefoldRepetition));
+    }
+
+    #[test]
+    fn fifty_move_rule_can_be_claimed() {
+        let mut chess = Chess::new("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80").unwrap();
+        assert_eq!(chess.get_claimable_draw(), None);
+
+        play_san(&mut chess, &["Ra2"]);
+        assert_eq!(chess.get_status(), GameStatus::Ongoing);
+        assert_eq!(chess.get_claimable_draw(), Some(DrawReason::FiftyMoveRule));
+
+        // A pawn move resets the count
+        let mut chess = Chess::new("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80").unwrap();
+        play_san(&mut chess, &["e3"]);
+        assert_eq!(chess.get_claimable_draw(), None);
+    }
+
+    #[test]
+    fn seventy_five_move_rule_ends_the_game() {
+        assert_eq!(status_after("4k3/8/8/8/8/8/4P3/R3K3 w - - 148 100", &["Ra2"]), GameStatus::Ongoing);
+        assert_eq!(status_after("4k3/8/8/8/8/8/4P3/R3K3 w - - 149 100", &["Ra2"]), GameStatus::Draw(DrawReason::SeventyFiveMoveRule));
+    }
+
+    #[test]
+    fn insufficient_material_ends_the_game() {
+        let draw = GameStatus::Draw(DrawReason::InsufficientMaterial);
+
+        // King vs king
+        assert_eq!(status_after("4k3/8/8/8/8/8/8/3qK3 w - - 0 1", &["Kxd1"]), draw);
+        // King and bishop vs king
+        assert_eq!(status_after("4k3/8/8/8/8/8/8/2BqK3 w - - 0 1", &["Kxd1"]), draw);
+        // King and knight vs king
+        assert_eq!(status_after("4k3/8/8/8/8/8/8/1N1qK3 w - - 0 1", &["Kxd1"]), draw);
+        // Bishops on squares of the same color
+        assert_eq!(status_after("4kb2/8/8/8/8/8/8/2BqK3 w - - 0 1", &["Kxd1"]), draw);
+
+        // Bishops on squares of different colors, or two knights, can still mate
+        assert_eq!(status_after("4k1b1/8/8/8/8/8/8/2BqK3 w - - 0 1", &["Kxd1"]), GameStatus::Ongoing);
+        assert_eq!(status_after("4k3/8/8/8/8/8/8/1NNqK3 w - - 0 1", &["Kxd1"]), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn takeback_restores_pawn_taken_en_passant() {
+        let fen = "4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1";
+        let mut chess = Chess::new(fen).unwrap();
+        play_san(&mut chess, &["dxe6"]);
+        assert_eq!(chess.to_fen(), "4k3/8/4P3/8/8/8/8/4K3 b - - 0 1");
+
+        chess.takeback();
+        assert_eq!(chess.to_fen(), fen);
+    }
+
+    #[test]
+    fn redo_does_not_revive_a_finished_game() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        play_san(&mut chess, &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8", "e4"]);
+
+        chess.takeback();
+        assert!(chess.claim_draw());
+
+        assert_eq!(chess.redo(), None);
+        assert_eq!(chess.get_status(), GameStatus::Draw(DrawReason::ThreefoldRepetition));
+        assert_eq!(chess.get_played_moves().len(), 8);
+    }
+}
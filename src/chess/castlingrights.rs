@@ -0,0 +1,79 @@
+use crate::chess::PieceColor;
+use crate::chess::Square;
+
+// Home squares of the kings and rooks, using the same 0..63 indexing as the board (a8 = 0, h1 = 63).
+pub const WHITE_KING_HOME:      Square = 60;
+pub const BLACK_KING_HOME:      Square = 4;
+pub const WHITE_KINGSIDE_ROOK:  Square = 63;
+pub const WHITE_QUEENSIDE_ROOK: Square = 56;
+pub const BLACK_KINGSIDE_ROOK:  Square = 7;
+pub const BLACK_QUEENSIDE_ROOK: Square = 0;
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Hash)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub struct CastlingRights {
+    pub white_kingside:  bool,
+    pub white_queenside: bool,
+    pub black_kingside:  bool,
+    pub black_queenside: bool,
+}
+
+impl CastlingRights {
+    pub fn none() -> Self {
+        CastlingRights {
+            white_kingside:  false,
+            white_queenside: false,
+            black_kingside:  false,
+            black_queenside: false,
+        }
+    }
+
+    pub fn kingside(&self, color: PieceColor) -> bool {
+        match color {
+            PieceColor::WHITE => self.white_kingside,
+            PieceColor::BLACK => self.black_kingside,
+        }
+    }
+
+    pub fn queenside(&self, color: PieceColor) -> bool {
+        match color {
+            PieceColor::WHITE => self.white_queenside,
+            PieceColor::BLACK => self.black_queenside,
+        }
+    }
+
+    // Called with both the source and destination square of every move.
+    // A king or rook leaving its home square, or a rook being captured on it, loses the matching right(s) for good.
+    pub fn revoke_for_square(&mut self, square: Square) {
+        match square {
+            WHITE_KING_HOME      => { self.white_kingside = false; self.white_queenside = false; },
+            BLACK_KING_HOME      => { self.black_kingside = false; self.black_queenside = false; },
+            WHITE_KINGSIDE_ROOK  => self.white_kingside  = false,
+            WHITE_QUEENSIDE_ROOK => self.white_queenside = false,
+            BLACK_KINGSIDE_ROOK  => self.black_kingside  = false,
+            BLACK_QUEENSIDE_ROOK => self.black_queenside = false,
+            _ => {},
+        };
+    }
+
+    pub fn king_home(color: PieceColor) -> Square {
+        match color {
+            PieceColor::WHITE => WHITE_KING_HOME,
+            PieceColor::BLACK => BLACK_KING_HOME,
+        }
+    }
+
+    // Returns the (from, to) squares of the rook for a castling king move.
+    pub fn rook_squares_for_castle(king_src: Square, king_dst: Square) -> (Square, Square) {
+        match king_dst > king_src {
+            // King side: rook jumps from the h-file to the square the king crossed
+            true  => (king_src + 3, king_src + 1),
+            // Queen side: rook jumps from the a-file to the square the king crossed
+            false => (king_src - 4, king_src - 1),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+
+use crate::chess::{PieceName, CastlingRights, GameStatus};
+use crate::chess::{Chess, Piece, Square};
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub enum MoveKind {
+    Normal,
+    // Pawn moving two squares from its starting rank, leaves an en passant target behind
+    DoublePawnPush,
+    // Pawn capturing the pawn beside it, which is not on the destination square
+    EnPassant,
+    // King moving two squares, king side or queen side depending on direction
+    Castle,
+}
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub struct Move {
+    pub src: Square,
+    pub dst: Square,
+    // The piece being moved, before any promotion
+    pub piece: PieceName,
+    pub captured: Option<PieceName>,
+    pub promotion: Option<PieceName>,
+    pub kind: MoveKind,
+}
+
+impl Move {
+    pub fn is_capture(&self) -> bool {
+        self.captured.is_some()
+    }
+}
+
+// An entry in the move history: the move, plus the parts of the position it overwrote that cannot be
+// worked out from the move itself. Everything needed for unmake_move() to restore the position exactly.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct MoveRecord {
+    pub mv: Move,
+    // The piece that was taken and the square it stood on, as it really was on the board
+    pub captured: Option<(Square, Piece)>,
+    pub castling_rights: CastlingRights,
+    pub en_passant_square: Option<Square>,
+    pub halfmove_clock: u32,
+    pub status: GameStatus,
+}
+
+// Coordinate notation: source square, destination square and the promotion piece if any, e.g. e2e4 or e7e8q.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", Chess::square_name(self.src), Chess::square_name(self.dst))?;
+
+        if let Some(promotion) = self.promotion {
+            let ch = match promotion {
+                PieceName::QUEEN  => 'q',
+                PieceName::ROOK   => 'r',
+                PieceName::BISHOP => 'b',
+                PieceName::KNIGHT => 'n',
+                PieceName::PAWN | PieceName::KING => unreachable!("pawns only promote to queen, rook, bishop or knight"),
+            };
+            write!(f, "{ch}")?;
+        }
+
+        Ok(())
+    }
+}
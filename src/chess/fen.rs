@@ -0,0 +1,414 @@
+use std::fmt;
+
+use crate::chess::Chess;
+use crate::chess::{PieceColor, PieceName, CastlingRights, GameStatus};
+use crate::chess::Square;
+
+// The six space separated fields of a FEN string, in order.
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub enum FenField {
+    PiecePlacement,
+    SideToMove,
+    CastlingAvailability,
+    EnPassantSquare,
+    HalfmoveClock,
+    FullmoveNumber,
+}
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub enum FenError {
+    // A FEN string must have exactly six fields
+    WrongFieldCount(usize),
+    InvalidCharacter { field: FenField, character: char },
+    // The piece placement must describe exactly eight ranks
+    WrongRankCount(usize),
+    // Ranks are numbered 8 (top) down to 1, like on the board
+    RankTooLong(usize),
+    RankTooShort(usize),
+    // Each side needs exactly one king
+    WrongKingCount { color: PieceColor, count: usize },
+    // Pawns can never stand on the first or last rank
+    PawnOnBackRank(usize),
+    InvalidSideToMove(String),
+    // The en passant square must be the one a pawn of the side that just moved skipped with a double push
+    InvalidEnPassantSquare(String),
+    // The side that just moved cannot have left its own king in check
+    OpponentInCheck,
+    InvalidNumber { field: FenField, text: String },
+}
+
+impl fmt::Display for FenField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FenField::PiecePlacement       => "piece placement",
+            FenField::SideToMove           => "side to move",
+            FenField::CastlingAvailability => "castling availability",
+            FenField::EnPassantSquare      => "en passant square",
+            FenField::HalfmoveClock        => "halfmove clock",
+            FenField::FullmoveNumber       => "fullmove number",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(count)               => write!(f, "expected 6 fields, found {count}"),
+            FenError::InvalidCharacter { field, character } => write!(f, "invalid character '{character}' in {field} field"),
+            FenError::WrongRankCount(count)                => write!(f, "expected 8 ranks in piece placement field, found {count}"),
+            FenError::RankTooLong(rank)                    => write!(f, "rank {rank} describes more than 8 squares"),
+            FenError::RankTooShort(rank)                   => write!(f, "rank {rank} describes fewer than 8 squares"),
+            FenError::WrongKingCount { color, count }      => write!(f, "expected one {color:?} king, found {count}"),
+            FenError::PawnOnBackRank(rank)                 => write!(f, "pawn on rank {rank}"),
+            FenError::InvalidSideToMove(text)              => write!(f, "expected 'w' or 'b' as side to move, found '{text}'"),
+            FenError::InvalidEnPassantSquare(text)         => write!(f, "invalid en passant square '{text}'"),
+            FenError::OpponentInCheck                      => write!(f, "the side not to move is in check"),
+            FenError::InvalidNumber { field, text }        => write!(f, "invalid number '{text}' in {field} field"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Chess {
+    // Replaces the current game with the position described by fen.
+    // On error the current game is left untouched.
+    pub fn load_fen(&mut self, fen: &str) -> Result<(), FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 6 { return Err(FenError::WrongFieldCount(fields.len())); }
+
+        let mut chess = Chess::empty();
+
+        chess.parse_piece_placement(fields[0])?;
+
+        chess.turn = match fields[1] {
+            "w" => PieceColor::WHITE,
+            "b" => PieceColor::BLACK,
+            text => {
+                // A single wrong letter is reported as such, anything longer as the whole field
+                let mut chars = text.chars();
+                return Err(match (chars.next(), chars.next()) {
+                    (Some(character), None) => FenError::InvalidCharacter { field: FenField::SideToMove, character },
+                    _                       => FenError::InvalidSideToMove(text.to_string()),
+                });
+            },
+        };
+
+        chess.castling_rights = Self::parse_castling_availability(fields[2])?;
+        chess.en_passant_square = chess.parse_en_passant_square(fields[3])?;
+        chess.halfmove_clock = Self::parse_number(FenField::HalfmoveClock, fields[4])?;
+        chess.fullmove_number = Self::parse_number(FenField::FullmoveNumber, fields[5])?;
+
+        if chess.fullmove_number == 0 {
+            return Err(FenError::InvalidNumber { field: FenField::FullmoveNumber, text: fields[5].to_string() });
+        }
+
+        // Otherwise the side to move could capture the king
+        let opponent_king_square = chess.get_king_square(chess.turn.opposite());
+        if Chess::board_attacks_square(&chess.board, opponent_king_square, chess.turn) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        chess.position_history = vec![chess.position_key()];
+        chess.status = GameStatus::Ongoing;
+        chess.update_status();
+
+        *self = chess;
+        Ok(())
+    }
+
+    // Writes the current position as a FEN string that load_fen() reads back to the same position.
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+
+        for row in 0..8 {
+            let mut empty_squares = 0;
+
+            for file in 0..8 {
+                match self.get_piece_at_square(row * 8 + file) {
+                    Some(piece) => {
+                        if empty_squares > 0 {
+                            placement.push_str(&empty_squares.to_string());
+                            empty_squares = 0;
+                        }
+                        placement.push(Self::fen_char_for_piece(piece));
+                    },
+                    None => empty_squares += 1,
+                };
+            }
+
+            if empty_squares > 0 { placement.push_str(&empty_squares.to_string()); }
+            if row < 7 { placement.push('/'); }
+        }
+
+        let side_to_move = match self.turn {
+            PieceColor::WHITE => "w",
+            PieceColor::BLACK => "b",
+        };
+
+        let rights = self.castling_rights;
+        let mut castling: String = [
+            (rights.white_kingside,  'K'),
+            (rights.white_queenside, 'Q'),
+            (rights.black_kingside,  'k'),
+            (rights.black_queenside, 'q'),
+        ].iter().filter(|(has_right, _)| *has_right).map(|(_, ch)| *ch).collect();
+
+        if castling.is_empty() { castling.push('-'); }
+
+        let en_passant = match self.en_passant_square {
+            Some(square) => Chess::square_name(square),
+            None         => "-".to_string(),
+        };
+
+        format!("{placement} {side_to_move} {castling} {en_passant} {} {}", self.halfmove_clock, self.fullmove_number)
+    }
+
+    fn fen_char_for_piece(piece: u8) -> char {
+        let ch = match Chess::get_name_for_piece(piece) {
+            PieceName::PAWN   => 'p',
+            PieceName::ROOK   => 'r',
+            PieceName::KNIGHT => 'n',
+            PieceName::BISHOP => 'b',
+            PieceName::QUEEN  => 'q',
+            PieceName::KING   => 'k',
+        };
+
+        match Chess::get_color_for_piece(piece) {
+            PieceColor::WHITE => ch.to_ascii_uppercase(),
+            PieceColor::BLACK => ch,
+        }
+    }
+
+    fn parse_piece_placement(&mut self, placement: &str) -> Result<(), FenError> {
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 { return Err(FenError::WrongRankCount(ranks.len())); }
+
+        let mut white_kings = 0;
+        let mut black_kings = 0;
+
+        for (row, rank) in ranks.iter().enumerate() {
+            let rank_number = 8 - row;
+            let mut file: usize = 0;
+
+            for ch in rank.chars() {
+
+                // For digits, skip that many empty squares
+                if let Some(digit) = ch.to_digit(/* Radix */ 10) {
+                    if digit == 0 || digit > 8 {
+                        return Err(FenError::InvalidCharacter { field: FenField::PiecePlacement, character: ch });
+                    }
+                    file += digit as usize;
+                    if file > 8 { return Err(FenError::RankTooLong(rank_number)); }
+                    continue;
+                }
+
+                // For chars setup the corresponding black or white piece at that square
+                let name = match ch.to_ascii_lowercase() {
+                    'p' => PieceName::PAWN,
+                    'r' => PieceName::ROOK,
+                    'n' => PieceName::KNIGHT,
+                    'b' => PieceName::BISHOP,
+                    'q' => PieceName::QUEEN,
+                    'k' => PieceName::KING,
+                     _  => return Err(FenError::InvalidCharacter { field: FenField::PiecePlacement, character: ch }),
+                };
+
+                let color = match ch.is_ascii_lowercase() {
+                    true  => PieceColor::BLACK,
+                    false => PieceColor::WHITE,
+                };
+
+                if file >= 8 { return Err(FenError::RankTooLong(rank_number)); }
+
+                if name == PieceName::PAWN && (rank_number == 1 || rank_number == 8) {
+                    return Err(FenError::PawnOnBackRank(rank_number));
+                }
+
+                let square: Square = row * 8 + file;
+                self.set_piece_at_square(square, name as u8 | color as u8);
+
+                if name == PieceName::KING {
+                    self.update_king_square(square, color);
+                    match color {
+                        PieceColor::WHITE => white_kings += 1,
+                        PieceColor::BLACK => black_kings += 1,
+                    };
+                }
+
+                file += 1;
+            }
+
+            if file < 8 { return Err(FenError::RankTooShort(rank_number)); }
+        }
+
+        if white_kings != 1 { return Err(FenError::WrongKingCount { color: PieceColor::WHITE, count: white_kings }); }
+        if black_kings != 1 { return Err(FenError::WrongKingCount { color: PieceColor::BLACK, count: black_kings }); }
+
+        Ok(())
+    }
+
+    fn parse_castling_availability(field: &str) -> Result<CastlingRights, FenError> {
+        let mut rights = CastlingRights::none();
+        if field == "-" { return Ok(rights); }
+
+        for ch in field.chars() {
+            let right = match ch {
+                'K' => &mut rights.white_kingside,
+                'Q' => &mut rights.white_queenside,
+                'k' => &mut rights.black_kingside,
+                'q' => &mut rights.black_queenside,
+                 _  => return Err(FenError::InvalidCharacter { field: FenField::CastlingAvailability, character: ch }),
+            };
+
+            // Each right may only be listed once
+            if *right {
+                return Err(FenError::InvalidCharacter { field: FenField::CastlingAvailability, character: ch });
+            }
+            *right = true;
+        }
+
+        Ok(rights)
+    }
+
+    // Needs the piece placement and side to move to be parsed already.
+    fn parse_en_passant_square(&self, field: &str) -> Result<Option<Square>, FenError> {
+        if field == "-" { return Ok(None); }
+
+        let square = match Chess::square_from_name(field) {
+            Some(square) => square,
+            None => {
+                // Point at the first character that cannot be part of a square name
+                let invalid_character = field.chars().enumerate().find(|&(index, ch)| match index {
+                    0 => !('a'..='h').contains(&ch),
+                    1 => !('1'..='8').contains(&ch),
+                    _ => true,
+                });
+
+                return Err(match invalid_character {
+                    Some((_, character)) => FenError::InvalidCharacter { field: FenField::EnPassantSquare, character },
+                    None                 => FenError::InvalidEnPassantSquare(field.to_string()),
+                });
+            },
+        };
+
+        // The skipped square of a double pawn push is on the 6th rank when white is to move, and on the 3rd when black is.
+        // The pawn that made the push stands one square further on, and the square it started from is empty again.
+        let (expected_row, pawn_square, start_square) = match self.turn {
+            PieceColor::WHITE => (2, square + 8, square.wrapping_sub(8)),
+            PieceColor::BLACK => (5, square.wrapping_sub(8), square + 8),
+        };
+        if square / 8 != expected_row { return Err(FenError::InvalidEnPassantSquare(field.to_string())); }
+
+        let pushed_pawn = PieceName::PAWN as u8 | self.turn.opposite() as u8;
+        let is_valid = self.get_piece_at_square(pawn_square) == Some(pushed_pawn)
+            && self.is_square_empty(square)
+            && self.is_square_empty(start_square);
+
+        match is_valid {
+            true  => Ok(Some(square)),
+            false => Err(FenError::InvalidEnPassantSquare(field.to_string())),
+        }
+    }
+
+    fn parse_number(field: FenField, text: &str) -> Result<u32, FenError> {
+        if let Some(ch) = text.chars().find(|ch| !ch.is_ascii_digit()) {
+            return Err(FenError::InvalidCharacter { field, character: ch });
+        }
+
+        text.parse().map_err(|_| FenError::InvalidNumber { field, text: text.to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FenError, FenField};
+    use crate::chess::{Chess, PieceColor, STARTING_FEN};
+
+    fn error(fen: &str) -> FenError {
+        Chess::new(fen).err().unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_to_fen() {
+        for fen in [
+            STARTING_FEN,
+            "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+            "r3k2r/8/8/8/3pP3/8/8/R3K2R b Kq e3 0 30",
+            "8/8/4k3/8/8/2K5/8/8 w - - 57 91",
+        ] {
+            assert_eq!(Chess::new(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_fields() {
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0"), FenError::WrongFieldCount(5));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::WrongRankCount(7));
+        assert_eq!(error("rnbqkbnr/pppppppp/8p/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::RankTooLong(6));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/7/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::RankTooShort(5));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0"),
+            FenError::InvalidNumber { field: FenField::FullmoveNumber, text: "0".to_string() });
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 99999999999 1"),
+            FenError::InvalidNumber { field: FenField::HalfmoveClock, text: "99999999999".to_string() });
+    }
+
+    #[test]
+    fn reports_the_offending_character() {
+        assert_eq!(error("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            FenError::InvalidCharacter { field: FenField::PiecePlacement, character: 'x' });
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"),
+            FenError::InvalidCharacter { field: FenField::SideToMove, character: 'x' });
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkk - 0 1"),
+            FenError::InvalidCharacter { field: FenField::CastlingAvailability, character: 'k' });
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq i6 0 1"),
+            FenError::InvalidCharacter { field: FenField::EnPassantSquare, character: 'i' });
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 1a 1"),
+            FenError::InvalidCharacter { field: FenField::HalfmoveClock, character: 'a' });
+
+        // A field that is too long is reported as a whole, not by one of its valid letters
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR ww KQkq - 0 1"), FenError::InvalidSideToMove("ww".to_string()));
+    }
+
+    #[test]
+    fn rejects_impossible_positions() {
+        assert_eq!(error("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1"),
+            FenError::WrongKingCount { color: PieceColor::BLACK, count: 0 });
+        assert_eq!(error("4k3/8/8/8/8/8/8/P3K3 w - - 0 1"), FenError::PawnOnBackRank(1));
+        assert_eq!(error("p3k3/8/8/8/8/8/8/4K3 w - - 0 1"), FenError::PawnOnBackRank(8));
+
+        // White to move could capture the black king straight away
+        assert_eq!(error("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"), FenError::OpponentInCheck);
+    }
+
+    #[test]
+    fn en_passant_square_needs_a_pawn_that_just_moved_two_squares() {
+        assert!(Chess::new("4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1").is_ok());
+        assert!(Chess::new("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").is_ok());
+
+        let invalid = FenError::InvalidEnPassantSquare("e6".to_string());
+        // No pawn in front of the square
+        assert_eq!(error("4k3/8/8/3P4/8/8/8/4K3 w - e6 0 1"), invalid);
+        // The pawn could not have come from e7
+        assert_eq!(error("4k3/4p3/8/3Pp3/8/8/8/4K3 w - e6 0 1"), invalid);
+        // The skipped square is taken
+        assert_eq!(error("4k3/8/4n3/3Pp3/8/8/8/4K3 w - e6 0 1"), invalid);
+        // Wrong rank for the side to move
+        assert_eq!(error("4k3/8/8/8/3pP3/8/8/4K3 w - e3 0 1"), FenError::InvalidEnPassantSquare("e3".to_string()));
+    }
+
+    #[test]
+    fn failed_load_keeps_current_game() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        assert!(chess.load_fen("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1").is_err());
+        assert_eq!(chess.to_fen(), STARTING_FEN);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::chess::PieceColor;
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub enum GameStatus {
+    Ongoing,
+    // Carries the color of the winner
+    Checkmate(PieceColor),
+    Stalemate,
+    // Carries the color of the winner, whose opponent ran out of time
+    TimeForfeit(PieceColor),
+    Draw(DrawReason),
+}
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub enum DrawReason {
+    // Claimable by either player
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    // Applied automatically
+    SeventyFiveMoveRule,
+    FivefoldRepetition,
+    InsufficientMaterial,
+    // A player ran out of time, but their opponent could never have checkmated them
+    TimeoutVsInsufficientMaterial,
+}
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        *self != GameStatus::Ongoing
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        match self {
+            GameStatus::Ongoing          => None,
+            GameStatus::Checkmate(color) => Some(GameResult::win_for(*color)),
+            GameStatus::Stalemate        => Some(GameResult::Draw),
+            GameStatus::TimeForfeit(color) => Some(GameResult::win_for(*color)),
+            GameStatus::Draw(_)          => Some(GameResult::Draw),
+        }
+    }
+}
+
+impl GameResult {
+    pub fn win_for(color: PieceColor) -> Self {
+        match color {
+            PieceColor::WHITE => GameResult::WhiteWins,
+            PieceColor::BLACK => GameResult::BlackWins,
+        }
+    }
+}
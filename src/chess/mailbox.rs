@@ -0,0 +1,58 @@
+use crate::chess::Square;
+
+// 10x12 mailbox board geometry.
+//
+// The 8x8 board sits inside a 10x12 grid with a border of two rows above and below and one column on each side.
+// Board squares keep their usual 0..63 index (a8 = 0, h1 = 63) and every border cell holds -1.
+// Moving by a mailbox offset from any board square either lands on another board square or in the border,
+// and the border catches even a knight's jump, so a move can never wrap from one edge of the board to the other.
+//
+// Offsets:
+// -11 -10  -9
+//  -1   #   1
+//   9  10  11
+const MAILBOX: [i32; 120] = [
+     -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,
+     -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,
+     -1,   0,   1,   2,   3,   4,   5,   6,   7,  -1,
+     -1,   8,   9,  10,  11,  12,  13,  14,  15,  -1,
+     -1,  16,  17,  18,  19,  20,  21,  22,  23,  -1,
+     -1,  24,  25,  26,  27,  28,  29,  30,  31,  -1,
+     -1,  32,  33,  34,  35,  36,  37,  38,  39,  -1,
+     -1,  40,  41,  42,  43,  44,  45,  46,  47,  -1,
+     -1,  48,  49,  50,  51,  52,  53,  54,  55,  -1,
+     -1,  56,  57,  58,  59,  60,  61,  62,  63,  -1,
+     -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,
+     -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,  -1,
+];
+
+// Where each board square lives in the mailbox
+const MAILBOX64: [i32; 64] = [
+    21, 22, 23, 24, 25, 26, 27, 28,
+    31, 32, 33, 34, 35, 36, 37, 38,
+    41, 42, 43, 44, 45, 46, 47, 48,
+    51, 52, 53, 54, 55, 56, 57, 58,
+    61, 62, 63, 64, 65, 66, 67, 68,
+    71, 72, 73, 74, 75, 76, 77, 78,
+    81, 82, 83, 84, 85, 86, 87, 88,
+    91, 92, 93, 94, 95, 96, 97, 98,
+];
+
+pub const KNIGHT_OFFSETS: [i32; 8] = [-21, -19, -12, -8, 8, 12, 19, 21];
+pub const BISHOP_OFFSETS: [i32; 4] = [-11, -9, 9, 11];
+pub const ROOK_OFFSETS:   [i32; 4] = [-10, -1, 1, 10];
+pub const KING_OFFSETS:   [i32; 8] = [-11, -10, -9, -1, 1, 9, 10, 11];
+
+// One step up the board (towards rank 8) and one step down (towards rank 1)
+pub const UP:   i32 = -10;
+pub const DOWN: i32 =  10;
+
+// The square reached by moving offset from square, or None if that lands off the board.
+pub fn offset_square(square: Square, offset: i32) -> Option<Square> {
+    let target = MAILBOX[(MAILBOX64[square] + offset) as usize];
+
+    match target {
+        -1 => None,
+        _  => Some(target as Square),
+    }
+}
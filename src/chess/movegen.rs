@@ -0,0 +1,254 @@
+use crate::chess::Chess;
+use crate::chess::{PieceColor, PieceName, CastlingRights, MoveValidator};
+use crate::chess::{Move, MoveKind};
+use crate::chess::{Piece, Square};
+use crate::chess::mailbox::{self, KNIGHT_OFFSETS, BISHOP_OFFSETS, ROOK_OFFSETS, KING_OFFSETS, UP, DOWN};
+
+const PROMOTION_PIECES: [PieceName; 4] = [PieceName::QUEEN, PieceName::ROOK, PieceName::BISHOP, PieceName::KNIGHT];
+
+impl Chess {
+    // Every legal move for the side to move.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+
+        for square in 0..=63 {
+            if let Some(piece) = self.get_piece_at_square(square) {
+                if self.is_turn_for_piece(piece) {
+                    self.generate_pseudo_legal_moves(square, piece, &mut moves);
+                }
+            }
+        }
+
+        moves.retain(|mv| !self.leaves_king_in_check(mv));
+        moves
+    }
+
+    // Every legal move of the piece on square. Empty if the square is empty or it is not that piece's turn.
+    pub fn legal_moves_from(&self, square: Square) -> Vec<Move> {
+        let mut moves = Vec::new();
+
+        if let Some(piece) = self.get_piece_at_square(square) {
+            if self.is_turn_for_piece(piece) {
+                self.generate_pseudo_legal_moves(square, piece, &mut moves);
+            }
+        }
+
+        moves.retain(|mv| !self.leaves_king_in_check(mv));
+        moves
+    }
+
+    // Describes moving the piece on src to dst as a Move, working out what kind of move it is and what it captures.
+    // Does not check whether the move is legal.
+    pub fn create_move(&self, src: Square, dst: Square, promotion: Option<PieceName>) -> Move {
+        let piece = Chess::get_name_for_piece(self.get_piece_at_square(src).unwrap());
+
+        let kind = match piece {
+            PieceName::PAWN if src.abs_diff(dst) == 16                          => MoveKind::DoublePawnPush,
+            PieceName::PAWN if src % 8 != dst % 8 && self.is_square_empty(dst) => MoveKind::EnPassant,
+            PieceName::KING if src.abs_diff(dst) == 2                           => MoveKind::Castle,
+            _                                                                   => MoveKind::Normal,
+        };
+
+        let captured = match kind {
+            MoveKind::EnPassant => Some(PieceName::PAWN),
+            _                   => self.get_piece_at_square(dst).map(Chess::get_name_for_piece),
+        };
+
+        Move { src, dst, piece, captured, promotion, kind }
+    }
+
+    // Makes the move on a copy of the board only, and reports whether the mover's own king is attacked afterwards.
+    pub fn leaves_king_in_check(&self, mv: &Move) -> bool {
+        let mut board = self.board;
+        let piece = board[mv.src].unwrap();
+        let color = Chess::get_color_for_piece(piece);
+
+        match mv.kind {
+            MoveKind::EnPassant => board[mv.src - mv.src % 8 + mv.dst % 8] = None,
+            MoveKind::Castle    => {
+                let (rook_src, rook_dst) = CastlingRights::rook_squares_for_castle(mv.src, mv.dst);
+                board[rook_dst] = board[rook_src].take();
+            },
+            MoveKind::Normal | MoveKind::DoublePawnPush => {},
+        };
+
+        board[mv.dst] = Some(piece);
+        board[mv.src] = None;
+
+        let king_square = match mv.piece {
+            PieceName::KING => mv.dst,
+            _               => self.get_king_square(color),
+        };
+
+        Self::board_attacks_square(&board, king_square, color.opposite())
+    }
+
+    // Looks outwards from target_square for attackers of attacker_color: pawns, knights and kings one step away,
+    // and bishops, rooks and queens along open lines.
+    pub fn board_attacks_square(board: &[Option<Piece>; 64], target_square: Square, attacker_color: PieceColor) -> bool {
+        let attacker = |name: PieceName| Some(name as u8 | attacker_color as u8);
+
+        // White pawns attack upwards, so they sit one row below the square they attack. Black pawns the opposite.
+        let pawn_offset = match attacker_color {
+            PieceColor::WHITE => DOWN,
+            PieceColor::BLACK => UP,
+        };
+        for side_step in [-1, 1] {
+            if let Some(square) = mailbox::offset_square(target_square, pawn_offset + side_step) {
+                if board[square] == attacker(PieceName::PAWN) { return true; }
+            }
+        }
+
+        for (step_name, offsets) in [(PieceName::KNIGHT, &KNIGHT_OFFSETS), (PieceName::KING, &KING_OFFSETS)] {
+            for &offset in offsets.iter() {
+                if let Some(square) = mailbox::offset_square(target_square, offset) {
+                    if board[square] == attacker(step_name) { return true; }
+                }
+            }
+        }
+
+        for (slider_name, offsets) in [(PieceName::BISHOP, &BISHOP_OFFSETS), (PieceName::ROOK, &ROOK_OFFSETS)] {
+            for &offset in offsets.iter() {
+                let mut current = target_square;
+                while let Some(square) = mailbox::offset_square(current, offset) {
+                    if let Some(piece) = board[square] {
+                        if Some(piece) == attacker(slider_name) || Some(piece) == attacker(PieceName::QUEEN) { return true; }
+                        break;
+                    }
+                    current = square;
+                }
+            }
+        }
+
+        false
+    }
+
+    fn generate_pseudo_legal_moves(&self, src: Square, piece: Piece, moves: &mut Vec<Move>) {
+        match Chess::get_name_for_piece(piece) {
+            PieceName::PAWN   => self.generate_pawn_moves(src, piece, moves),
+            PieceName::KNIGHT => self.generate_step_moves(src, piece, &KNIGHT_OFFSETS, moves),
+            PieceName::BISHOP => self.generate_slider_moves(src, piece, &BISHOP_OFFSETS, moves),
+            PieceName::ROOK   => self.generate_slider_moves(src, piece, &ROOK_OFFSETS, moves),
+            PieceName::QUEEN  => self.generate_slider_moves(src, piece, &KING_OFFSETS, moves),
+            PieceName::KING   => {
+                self.generate_step_moves(src, piece, &KING_OFFSETS, moves);
+                self.generate_castling_moves(src, piece, moves);
+            },
+        };
+    }
+
+    fn generate_pawn_moves(&self, src: Square, piece: Piece, moves: &mut Vec<Move>) {
+        let color = Chess::get_color_for_piece(piece);
+
+        // White heads up the board (towards row 0), black heads down.
+        let (forward, start_row, last_row) = match color {
+            PieceColor::WHITE => (UP,   6, 0),
+            PieceColor::BLACK => (DOWN, 1, 7),
+        };
+
+        // Pushes
+        if let Some(one_forward) = mailbox::offset_square(src, forward) {
+            if self.is_square_empty(one_forward) {
+                self.push_pawn_move(src, one_forward, last_row, moves);
+
+                if src / 8 == start_row {
+                    let two_forward = mailbox::offset_square(one_forward, forward).unwrap();
+                    if self.is_square_empty(two_forward) {
+                        moves.push(self.create_move(src, two_forward, None));
+                    }
+                }
+            }
+        }
+
+        // Captures, including en passant
+        for side_step in [-1, 1] {
+            if let Some(dst) = mailbox::offset_square(src, forward + side_step) {
+                let is_capture = match self.get_piece_at_square(dst) {
+                    Some(dst_piece) => Chess::get_color_for_piece(dst_piece) != color,
+                    None            => self.en_passant_square == Some(dst),
+                };
+
+                if is_capture {
+                    self.push_pawn_move(src, dst, last_row, moves);
+                }
+            }
+        }
+    }
+
+    // Pawn moves onto the last rank are pushed once for every piece the pawn can promote to.
+    fn push_pawn_move(&self, src: Square, dst: Square, last_row: Square, moves: &mut Vec<Move>) {
+        if dst / 8 == last_row {
+            for promotion in PROMOTION_PIECES {
+                moves.push(self.create_move(src, dst, Some(promotion)));
+            }
+        } else {
+            moves.push(self.create_move(src, dst, None));
+        }
+    }
+
+    // Knights and kings: one step in each direction, onto an empty square or an enemy piece.
+    fn generate_step_moves(&self, src: Square, piece: Piece, offsets: &[i32], moves: &mut Vec<Move>) {
+        let color = Chess::get_color_for_piece(piece);
+
+        for &offset in offsets {
+            if let Some(dst) = mailbox::offset_square(src, offset) {
+                match self.get_piece_at_square(dst) {
+                    Some(dst_piece) if Chess::get_color_for_piece(dst_piece) == color => {},
+                    _ => moves.push(self.create_move(src, dst, None)),
+                };
+            }
+        }
+    }
+
+    // Bishops, rooks and queens: keep stepping in each direction until blocked, capturing the first enemy piece in the way.
+    fn generate_slider_moves(&self, src: Square, piece: Piece, offsets: &[i32], moves: &mut Vec<Move>) {
+        let color = Chess::get_color_for_piece(piece);
+
+        for &offset in offsets {
+            let mut current = src;
+            while let Some(dst) = mailbox::offset_square(current, offset) {
+                match self.get_piece_at_square(dst) {
+                    Some(dst_piece) => {
+                        if Chess::get_color_for_piece(dst_piece) != color {
+                            moves.push(self.create_move(src, dst, None));
+                        }
+                        break;
+                    },
+                    None => moves.push(self.create_move(src, dst, None)),
+                };
+                current = dst;
+            }
+        }
+    }
+
+    fn generate_castling_moves(&self, src: Square, piece: Piece, moves: &mut Vec<Move>) {
+        let color = Chess::get_color_for_piece(piece);
+        if src != CastlingRights::king_home(color) { return; }
+
+        let enemy_color = color.opposite();
+        let is_attacked = |square: Square| MoveValidator::is_square_attacked(square, enemy_color, self);
+
+        // The king may not castle out of check
+        if is_attacked(src) { return; }
+
+        let rook = Some(PieceName::ROOK as u8 | color as u8);
+
+        if self.castling_rights.kingside(color) {
+            let (rook_square, _) = CastlingRights::rook_squares_for_castle(src, src + 2);
+            let path_is_empty = self.is_square_empty(src + 1) && self.is_square_empty(src + 2);
+
+            if self.get_piece_at_square(rook_square) == rook && path_is_empty && !is_attacked(src + 1) && !is_attacked(src + 2) {
+                moves.push(self.create_move(src, src + 2, None));
+            }
+        }
+
+        if self.castling_rights.queenside(color) {
+            let (rook_square, _) = CastlingRights::rook_squares_for_castle(src, src - 2);
+            let path_is_empty = self.is_square_empty(src - 1) && self.is_square_empty(src - 2) && self.is_square_empty(src - 3);
+
+            if self.get_piece_at_square(rook_square) == rook && path_is_empty && !is_attacked(src - 1) && !is_attacked(src - 2) {
+                moves.push(self.create_move(src, src - 2, None));
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::chess::Chess;
+use crate::chess::Move;
+
+impl Chess {
+    // Counts the leaf nodes of the legal move tree depth plies deep.
+    // Comparing against published counts is the standard way to check a move generator.
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 { return 1; }
+
+        let moves = self.legal_moves();
+        if depth == 1 { return moves.len() as u64; }
+
+        let mut nodes = 0;
+        for mv in moves {
+            self.make_move(mv);
+            nodes += self.perft(depth - 1);
+            self.unmake_move();
+        }
+
+        nodes
+    }
+
+    // perft() split up by the first move, for narrowing down which move a wrong count comes from.
+    pub fn divide(&mut self, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 { return Vec::new(); }
+
+        let mut counts = Vec::new();
+        for mv in self.legal_moves() {
+            self.make_move(mv);
+            counts.push((mv, self.perft(depth - 1)));
+            self.unmake_move();
+        }
+
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::{Chess, STARTING_FEN};
+
+    // Reference positions and node counts from https://www.chessprogramming.org/Perft_Results
+    const KIWIPETE:   &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    fn assert_perft(fen: &str, expected: &[u64]) {
+        let mut chess = Chess::new(fen).unwrap();
+
+        for (depth, &nodes) in (1..).zip(expected) {
+            assert_eq!(chess.perft(depth), nodes, "perft({depth}) of {fen}");
+            assert_eq!(chess.to_fen(), fen, "perft({depth}) left the position changed");
+        }
+    }
+
+    #[test]
+    fn perft_starting_position() {
+        assert_perft(STARTING_FEN, &[20, 400, 8902, 197281]);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        assert_perft(KIWIPETE, &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn perft_en_passant_and_pins() {
+        assert_perft(POSITION_3, &[14, 191, 2812, 43238]);
+    }
+
+    #[test]
+    fn perft_castling_and_promotion() {
+        assert_perft(POSITION_4, &[6, 264, 9467]);
+    }
+
+    #[test]
+    fn perft_promotion_with_check() {
+        assert_perft(POSITION_5, &[44, 1486, 62379]);
+    }
+
+    #[test]
+    fn perft_middlegame() {
+        assert_perft(POSITION_6, &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        let mut chess = Chess::new(KIWIPETE).unwrap();
+        let counts = chess.divide(2);
+
+        assert_eq!(counts.len(), 48);
+        assert_eq!(counts.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+    }
+}
@@ -0,0 +1,487 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::chess::Chess;
+use crate::chess::{PieceColor, FenError, GameResult, STARTING_FEN};
+use crate::chess::san::SanError;
+
+// The Seven Tag Roster every exported game starts with, and the value used for a tag that is not known.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event",  "?"),
+    ("Site",   "?"),
+    ("Date",   "????.??.??"),
+    ("Round",  "?"),
+    ("White",  "?"),
+    ("Black",  "?"),
+    ("Result", "*"),
+];
+
+// Export format keeps movetext lines below 80 characters
+const MAX_LINE_LENGTH: usize = 79;
+
+// One game read from a PGN file: its tag pairs, in file order, and the position after its main line.
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub chess: Chess,
+}
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub enum PgnErrorKind {
+    UnexpectedCharacter(char),
+    UnterminatedComment,
+    // A tag pair is written [Name "value"]
+    InvalidTag,
+    UnterminatedVariation,
+    // A variation replaces a move, so it cannot come before the first one
+    VariationWithoutMove,
+    InvalidFen(FenError),
+    InvalidMove(SanError),
+    // The move is legal on the board, but the game had already ended by checkmate, stalemate or a draw
+    MoveAfterGameEnd(String),
+}
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub struct PgnError {
+    // Both start at 1 and point at the start of the offending token
+    pub line: usize,
+    pub column: usize,
+    pub kind: PgnErrorKind,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+
+        match &self.kind {
+            PgnErrorKind::UnexpectedCharacter(ch)  => write!(f, "unexpected character '{ch}'"),
+            PgnErrorKind::UnterminatedComment      => write!(f, "comment is never closed with '}}'"),
+            PgnErrorKind::InvalidTag               => write!(f, "malformed tag pair, expected [Name \"value\"]"),
+            PgnErrorKind::UnterminatedVariation    => write!(f, "variation is never closed with ')'"),
+            PgnErrorKind::VariationWithoutMove     => write!(f, "variation before the first move"),
+            PgnErrorKind::InvalidFen(err)          => write!(f, "invalid FEN tag: {err}"),
+            PgnErrorKind::InvalidMove(err)         => write!(f, "{err}"),
+            PgnErrorKind::MoveAfterGameEnd(san)    => write!(f, "'{san}' is played after the game has ended"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl Chess {
+    // Writes the game so far as PGN: the Seven Tag Roster, then any other tags given, then the moves in SAN.
+    // Tags missing from tags get their unknown value. The Result tag follows the game's status once it is over.
+    pub fn to_pgn(&self, tags: &[(String, String)]) -> String {
+        let tag_value = |name: &str| tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str());
+
+        let result = match self.status.result() {
+            Some(result) => Self::pgn_result(result),
+            None         => tag_value("Result").unwrap_or("*"),
+        };
+
+        let start = self.position_at_ply(0);
+
+        let mut pgn = String::new();
+        let mut write_tag = |name: &str, value: &str| {
+            let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{name} \"{escaped}\"]\n"));
+        };
+
+        for (name, unknown) in SEVEN_TAG_ROSTER {
+            match name {
+                "Result" => write_tag(name, result),
+                _        => write_tag(name, tag_value(name).unwrap_or(unknown)),
+            };
+        }
+
+        let start_fen = start.to_fen();
+        if start_fen != STARTING_FEN {
+            write_tag("SetUp", "1");
+            write_tag("FEN", &start_fen);
+        }
+
+        for (name, value) in tags {
+            let is_written = SEVEN_TAG_ROSTER.iter().any(|(roster_name, _)| roster_name == name) || name == "SetUp" || name == "FEN";
+            if !is_written { write_tag(name, value); }
+        }
+
+        // Movetext
+        let mut tokens = Vec::new();
+        let mut number = start.fullmove_number;
+        let mut turn   = start.turn;
+
+        for (index, san) in self.played_moves_in_san().into_iter().enumerate() {
+            match turn {
+                PieceColor::WHITE               => tokens.push(format!("{number}.")),
+                PieceColor::BLACK if index == 0 => tokens.push(format!("{number}...")),
+                PieceColor::BLACK               => {},
+            };
+
+            tokens.push(san);
+
+            if turn == PieceColor::BLACK { number += 1; }
+            turn = turn.opposite();
+        }
+        tokens.push(result.to_string());
+
+        pgn.push('\n');
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+                pgn.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+
+            line_length += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+
+        pgn
+    }
+
+    // Reads every game in a PGN file, replaying each main line through the rules.
+    // Variations are replayed too, from the position before the move they replace, so an illegal move in one is an error.
+    pub fn from_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+        let mut reader = PgnReader::new(text);
+        let mut games = Vec::new();
+
+        while let Some(game) = reader.read_game()? {
+            games.push(game);
+        }
+
+        Ok(games)
+    }
+
+    fn pgn_result(result: GameResult) -> &'static str {
+        match result {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw      => "1/2-1/2",
+        }
+    }
+}
+
+// Walks through the PGN text a character at a time, keeping track of the line and column for error messages.
+struct PgnReader<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> PgnReader<'a> {
+    fn new(text: &'a str) -> Self {
+        PgnReader {
+            chars: text.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+
+        match ch {
+            '\n' => {
+                self.line += 1;
+                self.column = 1;
+            },
+            _ => self.column += 1,
+        };
+
+        Some(ch)
+    }
+
+    fn error(&self, kind: PgnErrorKind) -> PgnError {
+        PgnError { line: self.line, column: self.column, kind }
+    }
+
+    // Symbols are move numbers, moves in SAN and game results
+    fn is_symbol_char(ch: char) -> bool {
+        ch.is_ascii_alphanumeric() || "+#=:-/!?_".contains(ch)
+    }
+
+    // Skips whitespace, {brace comments}, ;rest of line comments and %escaped lines
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), PgnError> {
+        while let Some(ch) = self.peek() {
+            match ch {
+                _ if ch.is_whitespace() => { self.next(); },
+                '{' => {
+                    let start = self.error(PgnErrorKind::UnterminatedComment);
+                    self.next();
+                    loop {
+                        match self.next() {
+                            Some('}') => break,
+                            Some(_)   => {},
+                            None      => return Err(start),
+                        };
+                    }
+                },
+                ';' => self.skip_line(),
+                '%' if self.column == 1 => self.skip_line(),
+                _ => break,
+            };
+        }
+
+        Ok(())
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(ch) = self.next() {
+            if ch == '\n' { break; }
+        }
+    }
+
+    fn read_symbol(&mut self) -> String {
+        let mut symbol = String::new();
+
+        while let Some(ch) = self.peek() {
+            if !Self::is_symbol_char(ch) { break; }
+            symbol.push(ch);
+            self.next();
+        }
+
+        symbol
+    }
+
+    // Reads the next game, or None once only whitespace and comments are left.
+    fn read_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+        self.skip_whitespace_and_comments()?;
+        if self.peek().is_none() { return Ok(None); }
+
+        let mut tags = Vec::new();
+        let mut fen_tag = None;
+
+        while self.peek() == Some('[') {
+            let tag_start = self.error(PgnErrorKind::InvalidTag);
+            let (name, value) = self.read_tag()?;
+
+            if name == "FEN" { fen_tag = Some((value.clone(), tag_start)); }
+            tags.push((name, value));
+
+            self.skip_whitespace_and_comments()?;
+        }
+
+        let mut chess = match fen_tag {
+            Some((fen, tag_start)) => Chess::new(&fen).map_err(|err| PgnError { kind: PgnErrorKind::InvalidFen(err), ..tag_start })?,
+            None                   => Chess::new(STARTING_FEN).expect("Starting position FEN is valid"),
+        };
+
+        self.read_movetext(&mut chess, false)?;
+
+        Ok(Some(PgnGame { tags, chess }))
+    }
+
+    // [Name "value"], with \" and \\ escapes in the value
+    fn read_tag(&mut self) -> Result<(String, String), PgnError> {
+        let invalid_tag = self.error(PgnErrorKind::InvalidTag);
+        self.next();
+
+        while self.peek().is_some_and(|ch| ch == ' ' || ch == '\t') { self.next(); }
+        let name = self.read_symbol();
+        while self.peek().is_some_and(|ch| ch == ' ' || ch == '\t') { self.next(); }
+
+        if name.is_empty() || self.next() != Some('"') { return Err(invalid_tag); }
+
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('"')  => break,
+                Some('\\') => value.push(self.next().ok_or_else(|| invalid_tag.clone())?),
+                Some('\n') | None => return Err(invalid_tag),
+                Some(ch)   => value.push(ch),
+            };
+        }
+
+        while self.peek().is_some_and(|ch| ch == ' ' || ch == '\t') { self.next(); }
+        if self.next() != Some(']') { return Err(invalid_tag); }
+
+        Ok((name, value))
+    }
+
+    // Plays the moves onto chess until the game result, the end of the file, or the start of the next game.
+    // Inside a variation it stops at the closing ')' instead.
+    fn read_movetext(&mut self, chess: &mut Chess, in_variation: bool) -> Result<(), PgnError> {
+        // The position before the last move, where a variation on that move starts
+        let mut before_last_move: Option<Chess> = None;
+
+        loop {
+            self.skip_whitespace_and_comments()?;
+            let token_start = (self.line, self.column);
+            let error_here = |kind| PgnError { line: token_start.0, column: token_start.1, kind };
+
+            match self.peek() {
+                None if in_variation => return Err(error_here(PgnErrorKind::UnterminatedVariation)),
+                None                 => return Ok(()),
+
+                Some('(') => {
+                    self.next();
+                    let mut variation = before_last_move.clone().ok_or_else(|| error_here(PgnErrorKind::VariationWithoutMove))?;
+
+                    self.read_movetext(&mut variation, true).map_err(|err| match err.kind {
+                        // Point at the opening bracket rather than the end of the file
+                        PgnErrorKind::UnterminatedVariation => error_here(PgnErrorKind::UnterminatedVariation),
+                        _                                   => err,
+                    })?;
+                },
+                Some(')') if in_variation => {
+                    self.next();
+                    return Ok(());
+                },
+
+                // A tag pair with no result before it starts the next game
+                Some('[') if !in_variation => return Ok(()),
+
+                // Numeric Annotation Glyph, e.g. $1
+                Some('$') => {
+                    self.next();
+                    let nag = self.read_symbol();
+                    if nag.is_empty() || !nag.chars().all(|ch| ch.is_ascii_digit()) {
+                        return Err(error_here(PgnErrorKind::UnexpectedCharacter('$')));
+                    }
+                },
+
+                // The dots after move numbers
+                Some('.') => { self.next(); },
+
+                Some('*') if !in_variation => {
+                    self.next();
+                    return Ok(());
+                },
+
+                // The game ended while a variation was still open
+                Some('[') | Some('*') => return Err(error_here(PgnErrorKind::UnterminatedVariation)),
+
+                Some(ch) if Self::is_symbol_char(ch) => {
+                    let symbol = self.read_symbol();
+
+                    match symbol.as_str() {
+                        "1-0" | "0-1" | "1/2-1/2" if !in_variation => return Ok(()),
+                        "1-0" | "0-1" | "1/2-1/2" => return Err(error_here(PgnErrorKind::UnterminatedVariation)),
+                        _ if symbol.chars().all(|ch| ch.is_ascii_digit()) => {}, // Move number
+                        _ if chess.get_status().is_over() => return Err(error_here(PgnErrorKind::MoveAfterGameEnd(symbol))),
+                        _ => {
+                            let mv = chess.parse_san(&symbol).map_err(|err| error_here(PgnErrorKind::InvalidMove(err)))?;
+                            before_last_move = Some(chess.clone());
+                            chess.attempt_move(mv.src, mv.dst, mv.promotion);
+                        },
+                    };
+                },
+
+                Some(ch) => return Err(error_here(PgnErrorKind::UnexpectedCharacter(ch))),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PgnError, PgnErrorKind};
+    use crate::chess::san::SanError;
+    use crate::chess::{play_san, Chess, GameStatus, PieceColor, STARTING_FEN};
+
+    const SCHOLARS_MATE: &str = "\
+[Event \"?\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"?\"]
+[Black \"?\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0
+";
+
+    #[test]
+    fn exports_seven_tag_roster_and_result() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        play_san(&mut chess, &["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"]);
+
+        assert_eq!(chess.to_pgn(&[]), SCHOLARS_MATE);
+    }
+
+    #[test]
+    fn exports_setup_position_and_extra_tags() {
+        let fen = "4k3/8/8/8/8/8/8/R3K3 b - - 0 40";
+        let mut chess = Chess::new(fen).unwrap();
+        play_san(&mut chess, &["Kd7", "Ra7+"]);
+
+        let tags = vec![("White".to_string(), "Alice \"A\"".to_string()), ("Annotator".to_string(), "Bob".to_string())];
+        let pgn = chess.to_pgn(&tags);
+
+        assert!(pgn.contains("[White \"Alice \\\"A\\\"\"]\n"));
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 40\"]\n[Annotator \"Bob\"]\n"));
+        assert!(pgn.ends_with("\n40... Kd7 41. Ra7+ *\n"));
+    }
+
+    #[test]
+    fn round_trips_through_export_and_import() {
+        let mut chess = Chess::new(STARTING_FEN).unwrap();
+        play_san(&mut chess, &["d4", "d5", "c4", "dxc4", "e4", "b5", "a4", "c6", "axb5", "cxb5", "Qf3", "Nc6"]);
+
+        let games = Chess::from_pgn(&chess.to_pgn(&[])).unwrap();
+
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].chess.to_fen(), chess.to_fen());
+    }
+
+    #[test]
+    fn reads_comments_nags_and_variations() {
+        let pgn = "\
+[Event \"Test\"]
+[White \"Someone\"]
+
+% escaped line that is ignored
+1. e4 {best by test} e5 $1 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6!? ; rest of line
+3. Bb5 a6 (3... Nf6 4. O-O) 4. Ba4 *
+";
+        let games = Chess::from_pgn(pgn).unwrap();
+
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].tags, vec![("Event".to_string(), "Test".to_string()), ("White".to_string(), "Someone".to_string())]);
+        assert_eq!(games[0].chess.to_fen(), "r1bqkbnr/1ppp1ppp/p1n5/4p3/B3P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 1 4");
+    }
+
+    #[test]
+    fn reads_multiple_games() {
+        let pgn = format!("{SCHOLARS_MATE}\n[Event \"Second\"]\n[SetUp \"1\"]\n[FEN \"7k/8/6K1/8/8/8/8/R7 w - - 0 1\"]\n\n1. Ra8# 1-0\n\n1. d4 *");
+        let games = Chess::from_pgn(&pgn).unwrap();
+
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].chess.get_status(), GameStatus::Checkmate(PieceColor::WHITE));
+        assert_eq!(games[1].chess.get_status(), GameStatus::Checkmate(PieceColor::WHITE));
+        assert!(games[2].tags.is_empty());
+        assert_eq!(games[2].chess.get_turn(), PieceColor::BLACK);
+    }
+
+    #[test]
+    fn reports_line_and_column_of_errors() {
+        let error = |pgn: &str| Chess::from_pgn(pgn).err().unwrap();
+
+        assert_eq!(
+            error("[Event \"x\"]\n\n1. e4 e5 2. Ke3 *"),
+            PgnError { line: 3, column: 13, kind: PgnErrorKind::InvalidMove(SanError::IllegalMove("Ke3".to_string())) },
+        );
+        // The variation replaces 1. e4, so it starts with white to move
+        assert_eq!(error("1. e4 (1... e5) *"), PgnError { line: 1, column: 13, kind: PgnErrorKind::InvalidMove(SanError::IllegalMove("e5".to_string())) });
+        assert_eq!(error("1. e4 {never closed"), PgnError { line: 1, column: 7, kind: PgnErrorKind::UnterminatedComment });
+        assert_eq!(error("1. e4 e5\n2. Nf3 (2. f4 *"), PgnError { line: 2, column: 8, kind: PgnErrorKind::UnterminatedVariation });
+        assert_eq!(error("(1. e4) *"), PgnError { line: 1, column: 1, kind: PgnErrorKind::VariationWithoutMove });
+        assert_eq!(error("[Event x]"), PgnError { line: 1, column: 1, kind: PgnErrorKind::InvalidTag });
+        assert_eq!(error("1. e4 & *"), PgnError { line: 1, column: 7, kind: PgnErrorKind::UnexpectedCharacter('&') });
+        assert_eq!(error("1. f3 e5 2. g4 Qh4# 3. a3 *"), PgnError { line: 1, column: 24, kind: PgnErrorKind::MoveAfterGameEnd("a3".to_string()) });
+    }
+}
@@ -1,5 +1,8 @@
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
 #[derive(Eq)]
 #[derive(PartialEq)]
+#[derive(Hash)]
 #[derive(Copy)]
 #[derive(Clone)]
 #[repr(u8)]
@@ -8,6 +11,15 @@ pub enum PieceColor {
     BLACK = 128,
 }
 
+impl PieceColor {
+    pub fn opposite(self) -> PieceColor {
+        match self {
+            PieceColor::WHITE => PieceColor::BLACK,
+            PieceColor::BLACK => PieceColor::WHITE,
+        }
+    }
+}
+
 impl TryFrom<u8> for PieceColor {
     type Error = &'static str;
 
@@ -18,4 +30,4 @@ impl TryFrom<u8> for PieceColor {
                 _  => Err("Invalid u8 provided during PieceName conversion"),
         }
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,10 @@
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 #[derive(Eq)]
 #[derive(PartialEq)]
 #[derive(Hash)]
+#[derive(Copy)]
+#[derive(Clone)]
 #[repr(u8)]
 pub enum PieceName {
     PAWN = 1,
@@ -0,0 +1,17 @@
+use crate::chess::{CastlingRights, PieceColor};
+use crate::chess::{Piece, Square};
+
+// Everything that makes two positions "the same" for the repetition rules:
+// piece placement, side to move, castling rights and the en passant target.
+// The en passant target only counts when the side to move can actually capture on it.
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Hash)]
+#[derive(Clone)]
+pub struct PositionKey {
+    pub board: [Option<Piece>; 64],
+    pub turn: PieceColor,
+    pub castling_rights: CastlingRights,
+    pub en_passant_square: Option<Square>,
+}
@@ -0,0 +1,302 @@
+use std::fmt;
+
+use crate::chess::Chess;
+use crate::chess::{PieceName, Move, MoveKind};
+use crate::chess::Square;
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub enum SanError {
+    Empty,
+    // Not shaped like a SAN move at all, e.g. "Zz9"
+    InvalidSyntax(String),
+    // Well formed, but no legal move in this position matches it
+    IllegalMove(String),
+    // A pawn move onto the last rank that does not say what to promote to
+    MissingPromotion(String),
+    // More than one legal move matches; candidates holds each of them in full SAN
+    AmbiguousMove { san: String, candidates: Vec<String> },
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Empty                          => write!(f, "empty move"),
+            SanError::InvalidSyntax(san)             => write!(f, "'{san}' is not a move in SAN"),
+            SanError::IllegalMove(san)               => write!(f, "'{san}' is not a legal move in this position"),
+            SanError::MissingPromotion(san)          => write!(f, "'{san}' must name the piece to promote to, e.g. {san}=Q"),
+            SanError::AmbiguousMove { san, candidates } => write!(f, "'{san}' is ambiguous, it could be {}", candidates.join(" or ")),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+// A SAN move taken apart, before matching it against the legal moves.
+struct SanParts {
+    piece: PieceName,
+    from_file: Option<Square>,
+    from_row: Option<Square>,
+    is_capture: bool,
+    dst: Square,
+    promotion: Option<PieceName>,
+}
+
+impl Chess {
+    // Writes a legal move in Standard Algebraic Notation, e.g. Nf3, exd5, Rad1, e8=Q+ or O-O-O#.
+    pub fn move_to_san(&self, mv: &Move) -> String {
+        let mut san = match mv.kind {
+            MoveKind::Castle if mv.dst > mv.src => "O-O".to_string(),
+            MoveKind::Castle                    => "O-O-O".to_string(),
+            _                                   => self.san_without_suffix(mv),
+        };
+
+        let mut after = self.clone();
+        after.make_move(*mv);
+
+        if after.is_in_check() {
+            san.push(if after.has_legal_move() { '+' } else { '#' });
+        }
+
+        san
+    }
+
+    // Every move played so far in SAN, oldest first.
+    pub fn played_moves_in_san(&self) -> Vec<String> {
+        let mut replay = self.position_at_ply(0);
+
+        self.get_played_moves().iter().map(|mv| {
+            let san = replay.move_to_san(mv);
+            replay.make_move(*mv);
+            san
+        }).collect()
+    }
+
+    // Finds the one legal move that san describes.
+    // Check and mate suffixes and annotations like ! or ?! are accepted but not required.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        if text.is_empty() { return Err(SanError::Empty); }
+
+        let legal_moves = self.legal_moves();
+
+        // Castling, also accepting the zeros some programs write
+        let castle_kingside = match text {
+            "O-O"   | "0-0"   => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _                 => None,
+        };
+        if let Some(kingside) = castle_kingside {
+            return legal_moves.into_iter()
+                .find(|mv| mv.kind == MoveKind::Castle && (mv.dst > mv.src) == kingside)
+                .ok_or_else(|| SanError::IllegalMove(san.to_string()));
+        }
+
+        let parts = Self::split_san(text).ok_or_else(|| SanError::InvalidSyntax(san.to_string()))?;
+
+        let matches_square = |mv: &Move| {
+            mv.piece == parts.piece
+                && mv.dst == parts.dst
+                && mv.kind != MoveKind::Castle
+                && parts.from_file.is_none_or(|file| mv.src % 8 == file)
+                && parts.from_row.is_none_or(|row| mv.src / 8 == row)
+                && (!parts.is_capture || mv.is_capture())
+        };
+
+        let mut candidates: Vec<Move> = legal_moves.into_iter().filter(matches_square).collect();
+
+        if parts.promotion.is_none() && candidates.iter().any(|mv| mv.promotion.is_some()) {
+            return Err(SanError::MissingPromotion(san.to_string()));
+        }
+        candidates.retain(|mv| mv.promotion == parts.promotion);
+
+        match candidates.len() {
+            0 => Err(SanError::IllegalMove(san.to_string())),
+            1 => Ok(candidates[0]),
+            _ => Err(SanError::AmbiguousMove {
+                san: san.to_string(),
+                candidates: candidates.iter().map(|mv| self.move_to_san(mv)).collect(),
+            }),
+        }
+    }
+
+    // Piece letter, disambiguation, capture, destination and promotion, everything but the check suffix.
+    fn san_without_suffix(&self, mv: &Move) -> String {
+        let mut san = String::new();
+        let dst_name = Chess::square_name(mv.dst);
+
+        match mv.piece {
+            PieceName::PAWN => {
+                // Pawn captures name the file the pawn came from
+                if mv.is_capture() {
+                    san.push(Chess::square_name(mv.src).chars().next().unwrap());
+                }
+            },
+            piece => {
+                san.push(Self::san_letter_for_piece(piece));
+                san.push_str(&self.san_disambiguation(mv));
+            },
+        };
+
+        if mv.is_capture() { san.push('x'); }
+        san.push_str(&dst_name);
+
+        if let Some(promotion) = mv.promotion {
+            san.push('=');
+            san.push(Self::san_letter_for_piece(promotion));
+        }
+
+        san
+    }
+
+    // When another piece of the same kind could also move to the destination, name the source file if that
+    // tells them apart, otherwise the source rank, and the whole source square if neither does on its own.
+    fn san_disambiguation(&self, mv: &Move) -> String {
+        let others: Vec<Move> = self.legal_moves()
+            .into_iter()
+            .filter(|other| other.piece == mv.piece && other.dst == mv.dst && other.src != mv.src)
+            .collect();
+
+        let src_name = Chess::square_name(mv.src);
+
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|other| other.src % 8 != mv.src % 8) {
+            src_name[..1].to_string()
+        } else if others.iter().all(|other| other.src / 8 != mv.src / 8) {
+            src_name[1..].to_string()
+        } else {
+            src_name
+        }
+    }
+
+    // Takes apart everything but castling: [piece][from file][from rank][x]<destination>[=promotion]
+    fn split_san(text: &str) -> Option<SanParts> {
+        let mut chars: Vec<char> = text.chars().collect();
+
+        let piece = match chars.first()? {
+            'N' => PieceName::KNIGHT,
+            'B' => PieceName::BISHOP,
+            'R' => PieceName::ROOK,
+            'Q' => PieceName::QUEEN,
+            'K' => PieceName::KING,
+            _   => PieceName::PAWN,
+        };
+        if piece != PieceName::PAWN { chars.remove(0); }
+
+        // Promotion, written e8=Q or e8Q
+        let mut promotion = None;
+        if let Some(&last) = chars.last() {
+            if let Some(name) = Self::piece_for_san_letter(last) {
+                if piece != PieceName::PAWN { return None; }
+                chars.pop();
+                if chars.last() == Some(&'=') { chars.pop(); }
+                promotion = Some(name);
+            }
+        }
+
+        if chars.len() < 2 { return None; }
+        let dst_name: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let dst = Chess::square_from_name(&dst_name)?;
+
+        let is_capture = chars.last() == Some(&'x');
+        if is_capture { chars.pop(); }
+
+        let (from_file, from_row) = match chars.as_slice() {
+            []                 => (None, None),
+            [file @ 'a'..='h'] => (Some(*file as Square - 'a' as Square), None),
+            [rank @ '1'..='8'] => (None, Some('8' as Square - *rank as Square)),
+            [file @ 'a'..='h', rank @ '1'..='8'] => (Some(*file as Square - 'a' as Square), Some('8' as Square - *rank as Square)),
+            _                  => return None,
+        };
+
+        // A pawn names its source file only when capturing, and never its rank
+        if piece == PieceName::PAWN && (from_row.is_some() || from_file.is_some() != is_capture) { return None; }
+
+        Some(SanParts { piece, from_file, from_row, is_capture, dst, promotion })
+    }
+
+    fn san_letter_for_piece(piece: PieceName) -> char {
+        match piece {
+            PieceName::KNIGHT => 'N',
+            PieceName::BISHOP => 'B',
+            PieceName::ROOK   => 'R',
+            PieceName::QUEEN  => 'Q',
+            PieceName::KING   => 'K',
+            PieceName::PAWN   => unreachable!("pawns have no letter in SAN"),
+        }
+    }
+
+    fn piece_for_san_letter(letter: char) -> Option<PieceName> {
+        match letter {
+            'N' => Some(PieceName::KNIGHT),
+            'B' => Some(PieceName::BISHOP),
+            'R' => Some(PieceName::ROOK),
+            'Q' => Some(PieceName::QUEEN),
+            _   => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SanError;
+    use crate::chess::{Chess, STARTING_FEN};
+
+    fn san_for(fen: &str, san: &str) -> String {
+        let chess = Chess::new(fen).unwrap();
+        let mv = chess.parse_san(san).unwrap();
+        chess.move_to_san(&mv)
+    }
+
+    #[test]
+    fn formats_simple_moves() {
+        assert_eq!(san_for(STARTING_FEN, "e4"), "e4");
+        assert_eq!(san_for(STARTING_FEN, "Nf3"), "Nf3");
+        assert_eq!(san_for("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", "exd5"), "exd5");
+    }
+
+    #[test]
+    fn formats_castling_promotion_and_en_passant() {
+        assert_eq!(san_for("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O"), "O-O");
+        assert_eq!(san_for("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "0-0-0"), "O-O-O");
+        assert_eq!(san_for("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", "e8=N"), "e8=N");
+        assert_eq!(san_for("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", "e8Q"), "e8=Q");
+        assert_eq!(san_for("4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 2", "dxe6"), "dxe6");
+    }
+
+    #[test]
+    fn formats_check_and_mate() {
+        // Fool's mate
+        assert_eq!(san_for("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2", "Qh4"), "Qh4#");
+        assert_eq!(san_for("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "Ra8"), "Ra8+");
+    }
+
+    #[test]
+    fn disambiguates_by_file_rank_or_square() {
+        // Rooks on a1 and h1 share a rank, so the file tells them apart
+        assert_eq!(san_for("4k3/8/8/8/8/8/4K3/R6R w - - 0 1", "Rad1"), "Rad1");
+        // Rooks on a1 and a5 share a file, so the rank tells them apart
+        assert_eq!(san_for("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "R1a3"), "R1a3");
+        // Queens on h4, e4 and h1 all reach e1: h4 shares a file with h1 and a rank with e4
+        assert_eq!(san_for("1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1", "Qh4e1"), "Qh4e1");
+    }
+
+    #[test]
+    fn reports_bad_moves() {
+        let chess = Chess::new("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+
+        assert_eq!(chess.parse_san(""), Err(SanError::Empty));
+        assert_eq!(chess.parse_san("Zz9"), Err(SanError::InvalidSyntax("Zz9".to_string())));
+        assert_eq!(chess.parse_san("Nf3"), Err(SanError::IllegalMove("Nf3".to_string())));
+        assert_eq!(
+            chess.parse_san("Rd1"),
+            Err(SanError::AmbiguousMove { san: "Rd1".to_string(), candidates: vec!["Rad1".to_string(), "Rhd1".to_string()] }),
+        );
+
+        let chess = Chess::new("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        assert_eq!(chess.parse_san("e8"), Err(SanError::MissingPromotion("e8".to_string())));
+    }
+}
@@ -0,0 +1,399 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::chess::PieceColor;
+
+// Time given back to a player for each move they make
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub enum Bonus {
+    None,
+    // Fischer: added after every move, so time can build up
+    Increment(Duration),
+    // Bronstein: the time used on a move is given back, up to this much
+    Bronstein(Duration),
+    // US delay: the clock only starts counting down once this much of the move has passed
+    Delay(Duration),
+}
+
+// One period of a time control: an amount of time for a number of moves, or for the rest of the game
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub struct Stage {
+    // None for the rest of the game
+    moves: Option<u32>,
+    time:  Duration,
+    bonus: Bonus,
+}
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub struct TimeControl {
+    stages: Vec<Stage>,
+}
+
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+pub enum TimeControlError {
+    Empty,
+    InvalidStage(String),
+    // Only the last stage may last for the rest of the game
+    UnreachableStage(String),
+}
+
+impl fmt::Display for TimeControlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeControlError::Empty                   => write!(f, "empty time control"),
+            TimeControlError::InvalidStage(stage)     => write!(f, "invalid time control stage {stage:?}, expected e.g. 5, 3+2, 40/90+30, 25d5 or 25b5"),
+            TimeControlError::UnreachableStage(stage) => write!(f, "stage {stage:?} is never reached, the stage before it has no move count"),
+        }
+    }
+}
+
+impl std::error::Error for TimeControlError {}
+
+impl TimeControl {
+    // Stages are separated by commas, e.g. 40/90+30,30+30 for 90 minutes for the first 40 moves, then 30 minutes
+    // for the rest of the game, with 30 seconds added per move throughout. Each stage is [moves/]minutes, followed by
+    // +seconds for an increment, dseconds for a US delay or bseconds for a Bronstein delay.
+    // A last stage with a move count starts over once its moves are made, so 40/120 gives 2 hours per 40 moves.
+    pub fn parse(text: &str) -> Result<Self, TimeControlError> {
+        if text.trim().is_empty() { return Err(TimeControlError::Empty); }
+
+        let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+        let mut stages = Vec::new();
+
+        for (index, part) in parts.iter().enumerate() {
+            let stage = Self::parse_stage(part).ok_or_else(|| TimeControlError::InvalidStage(part.to_string()))?;
+
+            if index > 0 && stages.last().is_some_and(|previous: &Stage| previous.moves.is_none()) {
+                return Err(TimeControlError::UnreachableStage(part.to_string()));
+            }
+
+            stages.push(stage);
+        }
+
+        Ok(TimeControl { stages })
+    }
+
+    fn parse_stage(text: &str) -> Option<Stage> {
+        let (moves, rest) = match text.split_once('/') {
+            Some((moves, rest)) => (Some(moves.parse::<u32>().ok().filter(|&moves| moves > 0)?), rest),
+            None                => (None, text),
+        };
+
+        let (minutes, bonus) = match rest.find(['+', 'd', 'b']) {
+            Some(index) => {
+                let seconds = Self::parse_duration(&rest[index + 1..], 1.0)?;
+                let bonus = match &rest[index..index + 1] {
+                    "+" => Bonus::Increment(seconds),
+                    "d" => Bonus::Delay(seconds),
+                    _   => Bonus::Bronstein(seconds),
+                };
+                (&rest[..index], bonus)
+            },
+            None => (rest, Bonus::None),
+        };
+
+        let time = Self::parse_duration(minutes, 60.0)?;
+        if time.is_zero() { return None; }
+
+        Some(Stage { moves, time, bonus })
+    }
+
+    // A non-negative number of units of unit_seconds each, e.g. 1.5 minutes
+    fn parse_duration(text: &str, unit_seconds: f64) -> Option<Duration> {
+        // Negative, infinite and too large amounts are all refused here
+        let amount = text.parse::<f64>().ok()?;
+        Duration::try_from_secs_f64(amount * unit_seconds).ok()
+    }
+}
+
+// How far one player is into the time control
+#[derive(Debug)]
+#[derive(Copy)]
+#[derive(Clone)]
+struct SideClock {
+    remaining: Duration,
+    stage: usize,
+    moves_in_stage: u32,
+}
+
+// A clock for both players where only the side to move uses up time. Times are passed in rather than read,
+// so the clock can be driven by anything that counts time.
+pub struct ChessClock {
+    control: TimeControl,
+    // White's clock, then black's
+    sides: [SideClock; 2],
+    turn: PieceColor,
+    // When the side to move started on their move, while the clock is running
+    turn_started: Option<Instant>,
+}
+
+impl ChessClock {
+    pub fn new(control: TimeControl, turn: PieceColor) -> Self {
+        let side = SideClock {
+            remaining: control.stages[0].time,
+            stage: 0,
+            moves_in_stage: 0,
+        };
+
+        ChessClock {
+            control,
+            sides: [side; 2],
+            turn,
+            turn_started: None,
+        }
+    }
+
+    fn side_index(color: PieceColor) -> usize {
+        match color {
+            PieceColor::WHITE => 0,
+            PieceColor::BLACK => 1,
+        }
+    }
+
+    pub fn get_time_control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    pub fn get_turn(&self) -> PieceColor {
+        self.turn
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.turn_started.is_some()
+    }
+
+    // Starts the clock of the side to move
+    pub fn start(&mut self, now: Instant) {
+        if self.turn_started.is_none() {
+            self.turn_started = Some(now);
+        }
+    }
+
+    // Stops the clock for good, e.g. once the game is over, keeping the time used on the move so far
+    pub fn stop(&mut self, now: Instant) {
+        let index = Self::side_index(self.turn);
+        self.sides[index].remaining = self.remaining(self.turn, now);
+        self.turn_started = None;
+    }
+
+    // The time the side to move has spent on their move so far, counting towards their clock
+    fn charged_time(&self, now: Instant) -> Duration {
+        let Some(started) = self.turn_started else { return Duration::ZERO; };
+        let thinking = now.saturating_duration_since(started);
+
+        match self.current_stage(self.turn).bonus {
+            Bonus::Delay(delay) => thinking.saturating_sub(delay),
+            _                   => thinking,
+        }
+    }
+
+    fn current_stage(&self, color: PieceColor) -> Stage {
+        self.control.stages[self.sides[Self::side_index(color)].stage]
+    }
+
+    pub fn remaining(&self, color: PieceColor, now: Instant) -> Duration {
+        let remaining = self.sides[Self::side_index(color)].remaining;
+
+        match color == self.turn {
+            true  => remaining.saturating_sub(self.charged_time(now)),
+            false => remaining,
+        }
+    }
+
+    // The time color can count on getting back for each move in their current stage, however it is given
+    pub fn bonus_per_move(&self, color: PieceColor) -> Duration {
+        match self.current_stage(color).bonus {
+            Bonus::None => Duration::ZERO,
+            Bonus::Increment(bonus) | Bonus::Bronstein(bonus) | Bonus::Delay(bonus) => bonus,
+        }
+    }
+
+    // Moves color still has to make before the next stage's time is added, or None if the stage lasts for the rest of the game
+    pub fn moves_to_go(&self, color: PieceColor) -> Option<u32> {
+        let side = self.sides[Self::side_index(color)];
+        self.current_stage(color).moves.map(|moves| moves - side.moves_in_stage)
+    }
+
+    // The player whose time has run out, if any
+    pub fn flagged(&self, now: Instant) -> Option<PieceColor> {
+        [PieceColor::WHITE, PieceColor::BLACK].into_iter().find(|&color| self.remaining(color, now).is_zero())
+    }
+
+    // Ends the move of the side to move and starts their opponent's clock.
+    // A player who still had time left gets their bonus, and the next stage's time once the moves of this one are made.
+    pub fn press(&mut self, now: Instant) {
+        let stage     = self.current_stage(self.turn);
+        let thinking  = self.turn_started.map_or(Duration::ZERO, |started| now.saturating_duration_since(started));
+        let remaining = self.remaining(self.turn, now);
+
+        let side = &mut self.sides[Self::side_index(self.turn)];
+        side.remaining = remaining;
+
+        if !remaining.is_zero() {
+            let bonus = match stage.bonus {
+                Bonus::None                 => Duration::ZERO,
+                Bonus::Increment(increment) => increment,
+                Bonus::Bronstein(delay)     => thinking.min(delay),
+                Bonus::Delay(_)             => Duration::ZERO,
+            };
+            side.remaining = side.remaining.saturating_add(bonus);
+
+            side.moves_in_stage += 1;
+            if stage.moves == Some(side.moves_in_stage) {
+                // The last stage starts over when it runs out of moves
+                side.stage = (side.stage + 1).min(self.control.stages.len() - 1);
+                side.moves_in_stage = 0;
+                side.remaining = side.remaining.saturating_add(self.control.stages[side.stage].time);
+            }
+        }
+
+        self.turn = self.turn.opposite();
+        if self.turn_started.is_some() {
+            self.turn_started = Some(now);
+        }
+    }
+}
+
+// Hours only once there are any, and tenths of a second when time is short, e.g. 1:30:00, 4:05 or 0:09.3
+pub fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else if seconds >= 10 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{format_clock, Bonus, ChessClock, Stage, TimeControl, TimeControlError};
+    use crate::chess::PieceColor;
+
+    fn secs(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    // Starts a clock and plays moves taking the given number of seconds each, white first
+    fn clock_after(control: &str, move_seconds: &[u64]) -> (ChessClock, Instant) {
+        let mut now = Instant::now();
+        let mut clock = ChessClock::new(TimeControl::parse(control).unwrap(), PieceColor::WHITE);
+        clock.start(now);
+
+        for &seconds in move_seconds {
+            now += secs(seconds);
+            clock.press(now);
+        }
+
+        (clock, now)
+    }
+
+    #[test]
+    fn parses_stages_and_bonuses() {
+        let control = TimeControl::parse("40/90+30, 30+30").unwrap();
+        assert_eq!(control.stages, [
+            Stage { moves: Some(40), time: secs(90 * 60), bonus: Bonus::Increment(secs(30)) },
+            Stage { moves: None,     time: secs(30 * 60), bonus: Bonus::Increment(secs(30)) },
+        ]);
+
+        assert_eq!(TimeControl::parse("5").unwrap().stages[0].bonus, Bonus::None);
+        assert_eq!(TimeControl::parse("0.5d5").unwrap().stages[0], Stage { moves: None, time: secs(30), bonus: Bonus::Delay(secs(5)) });
+        assert_eq!(TimeControl::parse("25b10").unwrap().stages[0].bonus, Bonus::Bronstein(secs(10)));
+    }
+
+    #[test]
+    fn rejects_malformed_controls() {
+        assert_eq!(TimeControl::parse(" "), Err(TimeControlError::Empty));
+        assert_eq!(TimeControl::parse("0+2"), Err(TimeControlError::InvalidStage("0+2".to_string())));
+        assert_eq!(TimeControl::parse("40/"), Err(TimeControlError::InvalidStage("40/".to_string())));
+        assert_eq!(TimeControl::parse("5+x"), Err(TimeControlError::InvalidStage("5+x".to_string())));
+        assert_eq!(TimeControl::parse("90,30"), Err(TimeControlError::UnreachableStage("30".to_string())));
+        // Too long to fit in a Duration
+        assert_eq!(TimeControl::parse("1e20"), Err(TimeControlError::InvalidStage("1e20".to_string())));
+        assert_eq!(TimeControl::parse("5+-1"), Err(TimeControlError::InvalidStage("5+-1".to_string())));
+    }
+
+    #[test]
+    fn only_the_side_to_move_uses_time() {
+        let (clock, now) = clock_after("5+3", &[10, 4]);
+
+        assert_eq!(clock.remaining(PieceColor::WHITE, now), secs(293));
+        assert_eq!(clock.remaining(PieceColor::BLACK, now), secs(299));
+        assert_eq!(clock.remaining(PieceColor::WHITE, now + secs(20)), secs(273));
+        assert_eq!(clock.remaining(PieceColor::BLACK, now + secs(20)), secs(299));
+    }
+
+    #[test]
+    fn delays_only_count_time_past_the_delay() {
+        // US delay: a 3 second move costs nothing, an 8 second one costs 3
+        let (clock, now) = clock_after("1d5", &[3, 8]);
+        assert_eq!(clock.remaining(PieceColor::WHITE, now), secs(60));
+        assert_eq!(clock.remaining(PieceColor::BLACK, now), secs(57));
+
+        // Bronstein: the clock runs down in full, then up to the delay is given back
+        let (clock, now) = clock_after("1b5", &[3, 8]);
+        assert_eq!(clock.remaining(PieceColor::WHITE, now + secs(4)), secs(56));
+        assert_eq!(clock.remaining(PieceColor::BLACK, now), secs(57));
+    }
+
+    #[test]
+    fn next_stage_time_is_added_after_its_moves() {
+        let (clock, now) = clock_after("2/10,5+1", &[60, 60, 60, 60]);
+        assert_eq!(clock.remaining(PieceColor::WHITE, now), secs(480 + 300));
+
+        let (clock, now) = clock_after("2/10,5+1", &[60, 60, 60, 60, 60, 60]);
+        assert_eq!(clock.remaining(PieceColor::WHITE, now), secs(480 + 300 - 60 + 1));
+
+        // A repeating last stage hands out its time again
+        let (clock, now) = clock_after("1/1", &[30, 30]);
+        assert_eq!(clock.remaining(PieceColor::WHITE, now), secs(90));
+    }
+
+    #[test]
+    fn reports_moves_to_go_and_bonus_of_current_stage() {
+        let (clock, _) = clock_after("2/10,5+1", &[60]);
+        assert_eq!(clock.moves_to_go(PieceColor::WHITE), Some(1));
+        assert_eq!(clock.moves_to_go(PieceColor::BLACK), Some(2));
+        assert_eq!(clock.bonus_per_move(PieceColor::WHITE), Duration::ZERO);
+
+        let (clock, _) = clock_after("2/10,5+1", &[60, 60, 60]);
+        assert_eq!(clock.moves_to_go(PieceColor::WHITE), None);
+        assert_eq!(clock.bonus_per_move(PieceColor::WHITE), secs(1));
+        assert_eq!(clock.bonus_per_move(PieceColor::BLACK), Duration::ZERO);
+    }
+
+    #[test]
+    fn flags_the_player_out_of_time() {
+        let (clock, now) = clock_after("1+5", &[20]);
+        assert_eq!(clock.flagged(now + secs(59)), None);
+        assert_eq!(clock.flagged(now + secs(60)), Some(PieceColor::BLACK));
+
+        // No increment once the flag has fallen, even if the move was made
+        let (clock, now) = clock_after("1+5", &[61]);
+        assert_eq!(clock.flagged(now), Some(PieceColor::WHITE));
+    }
+
+    #[test]
+    fn formats_times_for_display() {
+        assert_eq!(format_clock(secs(5400)), "1:30:00");
+        assert_eq!(format_clock(secs(245)), "4:05");
+        assert_eq!(format_clock(Duration::from_millis(9_340)), "0:09.3");
+    }
+}
@@ -0,0 +1,279 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::chess::{Chess, Move};
+
+mod evaluate;
+mod worker;
+
+use crate::engine::evaluate::{evaluate, piece_value};
+pub use crate::engine::worker::{EngineWorker, EngineReport};
+
+// Score of being checkmated right now. Mates further away score a little less, so shorter mates are preferred.
+const MATE_SCORE: i32 = 100_000;
+const INFINITY: i32 = MATE_SCORE + 1;
+
+const MAX_DEPTH: u32 = 64;
+
+// The clock and the stop flag are only looked at every this many nodes, reading them is not free
+const NODES_PER_TIME_CHECK: u64 = 1024;
+
+// Share of the remaining time spent on a move when it is not known how many moves are left until the next time control
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+// When to stop searching. Without any limit the search goes on to MAX_DEPTH.
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub movetime: Option<Duration>,
+}
+
+// The outcome of the deepest search that was completed
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    // In centipawns, from the point of view of the side to move
+    pub score: i32,
+    pub depth: u32,
+    // The moves both sides are expected to play, starting with best_move
+    pub pv: Vec<Move>,
+    pub nodes: u64,
+    // Time taken since the search started
+    pub time: Duration,
+}
+
+impl SearchResult {
+    // Moves until mate if the score is a forced mate: positive when the side to move mates, negative when it is mated
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score.abs() < MATE_SCORE - MAX_DEPTH as i32 { return None; }
+
+        let moves = (MATE_SCORE - self.score.abs() + 1) / 2;
+        Some(self.score.signum() * moves)
+    }
+}
+
+// How long to search with time_left on the clock: an even share of it for the moves until the next time control,
+// plus most of the increment, but never so much that less than the move overhead is left on the clock.
+pub fn time_for_move(time_left: Duration, increment: Duration, moves_to_go: Option<u32>, move_overhead: Duration) -> Duration {
+    let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+    let share = time_left / moves_to_go + increment * 3 / 4;
+
+    share.min(time_left.saturating_sub(move_overhead))
+}
+
+struct Searcher<'a> {
+    chess: Chess,
+    deadline: Option<Instant>,
+    // Set by another thread to end the search early
+    stop: &'a AtomicBool,
+    nodes: u64,
+    // Set once the deadline has passed or a stop was asked for, after which every result is thrown away
+    stopped: bool,
+    // Best move of the previous iteration, searched first at the root
+    root_hint: Option<Move>,
+}
+
+// Finds the best move for the side to move with iterative deepening: alpha-beta searches of depth 1, 2, 3 and so on,
+// each one starting from the best move of the last, until a limit is reached, stop is set or a forced mate is found.
+// report is called with the result of every depth completed along the way.
+pub fn search(chess: &Chess, limits: &SearchLimits, stop: &AtomicBool, mut report: impl FnMut(&SearchResult)) -> SearchResult {
+    let start = Instant::now();
+    let mut searcher = Searcher {
+        chess: chess.clone(),
+        deadline: limits.movetime.map(|movetime| start + movetime),
+        stop,
+        nodes: 0,
+        stopped: false,
+        root_hint: None,
+    };
+
+    // Any legal move will do if not even the first iteration finishes in time
+    let mut result = SearchResult {
+        best_move: chess.legal_moves().first().copied(),
+        score: 0,
+        depth: 0,
+        pv: Vec::new(),
+        nodes: 0,
+        time: Duration::ZERO,
+    };
+    if result.best_move.is_none() || chess.get_status().is_over() { return result; }
+
+    for depth in 1..=limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH) {
+        let mut pv = Vec::new();
+        let score = searcher.negamax(depth, 0, -INFINITY, INFINITY, &mut pv);
+        if searcher.stopped { break; }
+
+        searcher.root_hint = pv.first().copied();
+        result = SearchResult {
+            best_move: pv.first().copied(),
+            score,
+            depth,
+            pv,
+            nodes: searcher.nodes,
+            time: start.elapsed(),
+        };
+        report(&result);
+
+        // The first mate found is the shortest one, searching deeper cannot improve on it
+        if score.abs() >= MATE_SCORE - MAX_DEPTH as i32 { break; }
+    }
+
+    result.nodes = searcher.nodes;
+    result.time  = start.elapsed();
+    result
+}
+
+impl Searcher<'_> {
+    fn count_node(&mut self) {
+        self.nodes += 1;
+
+        if self.nodes.is_multiple_of(NODES_PER_TIME_CHECK) {
+            let out_of_time = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            self.stopped = out_of_time || self.stop.load(Ordering::Relaxed);
+        }
+    }
+
+    // The fifty-move rule, a repetition or too little material to mate. A repetition counts as a draw
+    // the first time round, since whoever benefits from it could repeat it again.
+    fn is_draw(&self) -> bool {
+        self.chess.get_halfmove_clock() >= 100 || self.chess.is_repetition() || self.chess.has_insufficient_material()
+    }
+
+    // Alpha-beta search in negamax form: every score is from the point of view of the side to move,
+    // so the score of a move is the negated score of the position it leads to.
+    // Fills pv with the best line found from here, if any move scored above alpha.
+    fn negamax(&mut self, depth: u32, ply: usize, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        pv.clear();
+
+        if ply > 0 && self.is_draw() { return 0; }
+        if depth == 0 { return self.quiescence(alpha, beta); }
+
+        self.count_node();
+        if self.stopped { return 0; }
+
+        let mut moves = self.chess.legal_moves();
+        if moves.is_empty() {
+            return match self.chess.is_in_check() {
+                true  => -MATE_SCORE + ply as i32,
+                false => 0,
+            };
+        }
+        self.order_moves(&mut moves, ply);
+
+        let mut line = Vec::new();
+        for mv in moves {
+            self.chess.make_move(mv);
+            let score = -self.negamax(depth - 1, ply + 1, -beta, -alpha, &mut line);
+            self.chess.unmake_move();
+
+            if self.stopped { return 0; }
+
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&line);
+
+                // The opponent already has a better option earlier on and will never allow this position
+                if alpha >= beta { break; }
+            }
+        }
+
+        alpha
+    }
+
+    // Plays out captures and promotions until the position is quiet, so that the evaluation is never taken
+    // in the middle of an exchange. The side to move may also stand pat and keep the static evaluation.
+    fn quiescence(&mut self, mut alpha: i32, beta: i32) -> i32 {
+        self.count_node();
+        if self.stopped { return 0; }
+
+        let stand_pat = evaluate(&self.chess);
+        if stand_pat >= beta { return beta; }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = self.chess.legal_moves().into_iter().filter(|mv| mv.is_capture() || mv.promotion.is_some()).collect();
+        self.order_moves(&mut moves, usize::MAX);
+
+        for mv in moves {
+            self.chess.make_move(mv);
+            let score = -self.quiescence(-beta, -alpha);
+            self.chess.unmake_move();
+
+            if self.stopped { return 0; }
+
+            if score >= beta { return beta; }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+
+    // Searching the most promising moves first lets alpha-beta cut off more of the rest: the previous iteration's
+    // best move at the root, then captures of the most valuable pieces by the least valuable ones, then promotions.
+    fn order_moves(&self, moves: &mut [Move], ply: usize) {
+        moves.sort_by_key(|mv| {
+            if ply == 0 && Some(*mv) == self.root_hint {
+                return Reverse(INFINITY);
+            }
+
+            let capture   = mv.captured.map_or(0, |captured| 10 * piece_value(captured) - piece_value(mv.piece));
+            let promotion = mv.promotion.map_or(0, piece_value);
+            Reverse(capture + promotion)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    use super::{evaluate, search, time_for_move, SearchLimits, MATE_SCORE};
+    use crate::chess::{Chess, STARTING_FEN};
+
+    fn best_move(fen: &str, depth: u32) -> String {
+        let chess = Chess::new(fen).unwrap();
+        let result = search(&chess, &SearchLimits { depth: Some(depth), movetime: None }, &AtomicBool::new(false), |_| {});
+        result.best_move.unwrap().to_string()
+    }
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        assert_eq!(evaluate(&Chess::new(STARTING_FEN).unwrap()), 0);
+
+        // The same position with colors swapped and the board mirrored scores the same for the side to move
+        let white = evaluate(&Chess::new("r3k3/ppp5/8/8/3N4/8/5PPP/4K2R w - - 0 1").unwrap());
+        let black = evaluate(&Chess::new("4k2r/5ppp/8/3n4/8/8/PPP5/R3K3 b - - 0 1").unwrap());
+        assert_eq!(white, black);
+    }
+
+    #[test]
+    fn finds_back_rank_mate() {
+        let chess = Chess::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = search(&chess, &SearchLimits { depth: Some(3), movetime: None }, &AtomicBool::new(false), |_| {});
+        assert_eq!(result.best_move.unwrap().to_string(), "a1a8");
+        assert_eq!(result.score, MATE_SCORE - 1);
+        assert_eq!(result.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn keeps_the_move_overhead_in_hand() {
+        let overhead = Duration::from_millis(30);
+
+        assert_eq!(time_for_move(Duration::from_secs(40), Duration::ZERO, Some(20), overhead), Duration::from_secs(2));
+        assert_eq!(time_for_move(Duration::from_millis(100), Duration::from_secs(1), None, overhead), Duration::from_millis(70));
+        assert_eq!(time_for_move(Duration::from_millis(10), Duration::ZERO, None, overhead), Duration::ZERO);
+    }
+
+    #[test]
+    fn takes_hanging_piece_but_not_defended_one() {
+        assert_eq!(best_move("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2), "d2d5");
+        assert_ne!(best_move("4k3/4p3/3n4/8/8/8/3R4/4K3 w - - 0 1", 2), "d2d6");
+    }
+}
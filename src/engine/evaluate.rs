@@ -0,0 +1,145 @@
+use crate::chess::{Chess, PieceColor, PieceName};
+
+// Piece-square tables from the Simplified Evaluation Function by Tomasz Michniewski, in centipawns.
+// They are laid out like the board from white's side, a8 first, so white indexes them by square
+// and black by the square mirrored to the other side of the board.
+const PAWN_TABLE: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+const KNIGHT_TABLE: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+const BISHOP_TABLE: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+const ROOK_TABLE: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+const QUEEN_TABLE: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+// The king hides behind its pawns while there is enough material left to attack it
+const KING_MIDDLEGAME_TABLE: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+// and heads for the center once the board has emptied
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+// Below this much material besides kings and pawns, counting both sides, the king uses its endgame table
+const ENDGAME_MATERIAL: i32 = 2 * (500 + 330);
+
+pub fn piece_value(name: PieceName) -> i32 {
+    match name {
+        PieceName::PAWN   => 100,
+        PieceName::KNIGHT => 320,
+        PieceName::BISHOP => 330,
+        PieceName::ROOK   => 500,
+        PieceName::QUEEN  => 900,
+        PieceName::KING   => 0,
+    }
+}
+
+// Material and piece placement in centipawns, from the point of view of the side to move.
+pub fn evaluate(chess: &Chess) -> i32 {
+    let mut score = 0;
+    let mut officer_material = 0;
+
+    for square in 0..=63 {
+        if let Some(piece) = chess.get_piece_at_square(square) {
+            let name = Chess::get_name_for_piece(piece);
+            if name != PieceName::PAWN {
+                officer_material += piece_value(name);
+            }
+        }
+    }
+
+    let king_table = match officer_material <= ENDGAME_MATERIAL {
+        true  => &KING_ENDGAME_TABLE,
+        false => &KING_MIDDLEGAME_TABLE,
+    };
+
+    for square in 0..=63 {
+        if let Some(piece) = chess.get_piece_at_square(square) {
+            let name  = Chess::get_name_for_piece(piece);
+            let color = Chess::get_color_for_piece(piece);
+
+            let table = match name {
+                PieceName::PAWN   => &PAWN_TABLE,
+                PieceName::KNIGHT => &KNIGHT_TABLE,
+                PieceName::BISHOP => &BISHOP_TABLE,
+                PieceName::ROOK   => &ROOK_TABLE,
+                PieceName::QUEEN  => &QUEEN_TABLE,
+                PieceName::KING   => king_table,
+            };
+
+            let (table_square, sign) = match color {
+                PieceColor::WHITE => (square, 1),
+                PieceColor::BLACK => (square ^ 56, -1),
+            };
+
+            score += sign * (piece_value(name) + table[table_square]);
+        }
+    }
+
+    match chess.get_turn() {
+        PieceColor::WHITE => score,
+        PieceColor::BLACK => -score,
+    }
+}
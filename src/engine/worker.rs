@@ -0,0 +1,150 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::chess::Chess;
+use crate::engine::{self, SearchLimits, SearchResult};
+
+// Sent to the engine thread
+enum Command {
+    Search {
+        search_id: u64,
+        // Boxed to keep the other commands small
+        chess: Box<Chess>,
+        limits: SearchLimits,
+        stop: Arc<AtomicBool>,
+    },
+    Quit,
+}
+
+// Sent back from the engine thread, tagged with the search it belongs to
+#[derive(Debug)]
+pub enum EngineReport {
+    // The result of a depth that was just completed, while the search goes on
+    Progress { search_id: u64, result: SearchResult },
+    // The search is over, because it reached its limits or was stopped
+    BestMove { search_id: u64, result: SearchResult },
+}
+
+// Runs searches on a thread of its own, so whoever asks for a move can carry on in the meantime.
+// Searches run one at a time, in the order they were asked for.
+pub struct EngineWorker {
+    commands: Sender<Command>,
+    thread: Option<JoinHandle<()>>,
+    next_search_id: u64,
+    // Stop flag of the latest search
+    stop: Arc<AtomicBool>,
+}
+
+impl EngineWorker {
+    // Starts the engine thread. send_report is called on that thread with every report.
+    pub fn spawn(mut send_report: impl FnMut(EngineReport) + Send + 'static) -> Self {
+        let (commands, receiver) = mpsc::channel();
+
+        let thread = thread::Builder::new()
+            .name("engine".to_string())
+            .spawn(move || {
+                while let Ok(Command::Search { search_id, chess, limits, stop }) = receiver.recv() {
+                    let result = engine::search(&chess, &limits, &stop, |progress| {
+                        send_report(EngineReport::Progress { search_id, result: progress.clone() });
+                    });
+                    send_report(EngineReport::BestMove { search_id, result });
+                }
+            })
+            .expect("Engine thread can be spawned");
+
+        EngineWorker {
+            commands,
+            thread: Some(thread),
+            next_search_id: 0,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Starts searching chess once the search before it, which is stopped, has reported its best move.
+    // Returns the id the reports of this search carry.
+    pub fn search(&mut self, chess: Chess, limits: SearchLimits) -> u64 {
+        self.stop();
+
+        self.next_search_id += 1;
+        self.stop = Arc::new(AtomicBool::new(false));
+
+        let command = Command::Search {
+            search_id: self.next_search_id,
+            chess: Box::new(chess),
+            limits,
+            stop: Arc::clone(&self.stop),
+        };
+        self.commands.send(command).expect("Engine thread is running");
+
+        self.next_search_id
+    }
+
+    // Ends the latest search early. It still reports the best move found so far.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for EngineWorker {
+    fn drop(&mut self) {
+        self.stop();
+        let _ = self.commands.send(Command::Quit);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::{EngineWorker, EngineReport};
+    use crate::chess::Chess;
+    use crate::engine::SearchLimits;
+
+    #[test]
+    fn reports_progress_then_best_move() {
+        let (sender, reports) = mpsc::channel();
+        let mut worker = EngineWorker::spawn(move |report| sender.send(report).unwrap());
+
+        let chess = Chess::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let search_id = worker.search(chess, SearchLimits { depth: Some(3), movetime: None });
+
+        match reports.recv().unwrap() {
+            EngineReport::Progress { search_id: id, result } => {
+                assert_eq!(id, search_id);
+                assert_eq!(result.depth, 1);
+            },
+            report => panic!("expected progress first, got {report:?}"),
+        };
+
+        let best_move = reports.iter().find_map(|report| match report {
+            EngineReport::BestMove { search_id: id, result } if id == search_id => result.best_move,
+            _ => None,
+        });
+        assert_eq!(best_move.unwrap().to_string(), "a1a8");
+    }
+
+    #[test]
+    fn stopped_search_still_reports_a_move() {
+        let (sender, reports) = mpsc::channel();
+        let mut worker = EngineWorker::spawn(move |report| sender.send(report).unwrap());
+
+        // Without limits this would go on to the maximum depth
+        let first  = worker.search(Chess::new(crate::chess::STARTING_FEN).unwrap(), SearchLimits::default());
+        let second = worker.search(Chess::new("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap(), SearchLimits::default());
+        worker.stop();
+
+        let mut best_moves = reports.iter().filter_map(|report| match report {
+            EngineReport::BestMove { search_id, result } => Some((search_id, result.best_move)),
+            EngineReport::Progress { .. } => None,
+        });
+
+        assert!(matches!(best_moves.next(), Some((id, Some(_))) if id == first));
+        assert!(matches!(best_moves.next(), Some((id, Some(_))) if id == second));
+    }
+}
@@ -4,53 +4,236 @@ use speedy2d::window::{
     WindowHelper,
     WindowStartupInfo,
     MouseButton,
+    VirtualKeyCode,
+    KeyScancode,
+    ModifiersState,
 };
 use speedy2d::dimen::{UVec2, Vec2};
 use speedy2d::color::Color;
 
+use std::time::{Duration, Instant};
+
 use crate::uibundle::UIBundle;
+use crate::savegame::{self, SAVE_PATH};
 use crate::chess::Chess;
 use crate::chess::PieceColor;
+use crate::clock::{ChessClock, TimeControl};
+use crate::engine::{self, EngineWorker, EngineReport, SearchLimits, SearchResult};
+
+// How long the computer thinks about each of its moves in untimed games
+const COMPUTER_MOVE_TIME: Duration = Duration::from_secs(1);
+
+// Time the computer keeps in hand on its clock for the window to play its move
+const COMPUTER_MOVE_OVERHEAD: Duration = Duration::from_millis(50);
 
 pub struct GameWindowHandler {
     ui:    UIBundle,
     chess: Chess,
+    // PGN tag pairs of the game, kept when exporting it again
+    pgn_tags: Vec<(String, String)>,
+    // Ctrl, Alt, Shift and Logo keys currently held down
+    modifiers: ModifiersState,
+    // Only timed games have a clock
+    clock: Option<ChessClock>,
+    // The color the computer plays, in games against it
+    computer: Option<PieceColor>,
+    // Searches for the computer's moves, away from the window's thread
+    engine: EngineWorker,
+    // The search the computer's next move comes from, while it is thinking
+    computer_search: Option<u64>,
 }
 
 impl GameWindowHandler {
-    pub fn new(ui: UIBundle, chess: Chess) -> Self {
+    pub fn new(ui: UIBundle, chess: Chess, pgn_tags: Vec<(String, String)>, time_control: Option<TimeControl>, computer: Option<PieceColor>, engine: EngineWorker) -> Self {
+        let clock = time_control.map(|control| ChessClock::new(control, chess.get_turn()));
+
         GameWindowHandler {
             ui,
             chess,
+            pgn_tags,
+            modifiers: ModifiersState::default(),
+            clock,
+            computer,
+            engine,
+            computer_search: None,
+        }
+    }
+
+    // Ctrl on most platforms, Cmd on macOS
+    fn is_command_held(&self) -> bool {
+        self.modifiers.ctrl() || self.modifiers.logo()
+    }
+
+    // Keeps the clock in step with the game: a move hands the clock to the opponent, a flag fall ends the game,
+    // and a game that is over stops the clock.
+    fn update_clock(&mut self) {
+        let Some(clock) = &mut self.clock else { return; };
+        let now = Instant::now();
+
+        if self.chess.get_status().is_over() {
+            if clock.is_running() { clock.stop(now); }
+            return;
+        }
+
+        if clock.get_turn() != self.chess.get_turn() {
+            clock.press(now);
+        }
+
+        if let Some(color) = clock.flagged(now) {
+            self.chess.flag_fall(color);
+            clock.stop(now);
+            log::info!("{color:?} ran out of time: {:?}", self.chess.get_status());
+        }
+    }
+
+    fn is_computer_turn(&self) -> bool {
+        self.computer == Some(self.chess.get_turn()) && !self.chess.get_status().is_over()
+    }
+
+    // Sets the engine thinking when it is the computer's turn. Its move arrives later through on_user_event().
+    fn start_computer_search(&mut self) {
+        if self.computer_search.is_none() && self.is_computer_turn() {
+            let limits = SearchLimits { depth: None, movetime: Some(self.computer_move_time()) };
+            self.computer_search = Some(self.engine.search(self.chess.clone(), limits));
+        }
+    }
+
+    // On a running clock the computer budgets the time it has left like an engine in a GUI would
+    fn computer_move_time(&self) -> Duration {
+        match &self.clock {
+            Some(clock) if clock.is_running() => {
+                let color = self.chess.get_turn();
+                engine::time_for_move(clock.remaining(color, Instant::now()), clock.bonus_per_move(color), clock.moves_to_go(color), COMPUTER_MOVE_OVERHEAD)
+            },
+            _ => COMPUTER_MOVE_TIME,
+        }
+    }
+
+    // Forgets the search in progress, for when the game it was searching has changed under it
+    fn cancel_computer_search(&mut self) {
+        if self.computer_search.take().is_some() {
+            self.engine.stop();
+        }
+    }
+
+    fn play_computer_move(&mut self, result: SearchResult) {
+        self.computer_search = None;
+        if !self.is_computer_turn() { return; }
+
+        if let Some(mv) = result.best_move {
+            log::info!("Computer plays {mv} after {} nodes, depth {} score {}", result.nodes, result.depth, result.score);
+            self.chess.attempt_move(mv.src, mv.dst, mv.promotion);
+            self.update_clock();
+        }
+    }
+
+    fn takeback(&mut self) {
+        // Moves cannot be taken back against the clock
+        if self.clock.is_some() {
+            log::info!("Takebacks are off in timed games");
+            return;
+        }
+
+        self.cancel_computer_search();
+        self.ui.show_live_position();
+        if let Some(mv) = self.chess.takeback() {
+            log::info!("Took back {mv}");
+        }
+
+        // Against the computer, take back its reply as well as your move, so that it is your turn again
+        if self.is_computer_turn() {
+            if let Some(mv) = self.chess.takeback() {
+                log::info!("Took back {mv}");
+            }
+        }
+        self.ui.clear_selection();
+    }
+
+    fn redo(&mut self) {
+        if self.clock.is_some() {
+            log::info!("Takebacks are off in timed games");
+            return;
+        }
+
+        self.cancel_computer_search();
+        self.ui.show_live_position();
+        if let Some(mv) = self.chess.redo() {
+            log::info!("Redid {mv}");
+        }
+
+        // Against the computer, replay its reply as well, or let it find a new one if there is none
+        if self.is_computer_turn() {
+            if let Some(mv) = self.chess.redo() {
+                log::info!("Redid {mv}");
+            }
         }
+        self.ui.clear_selection();
     }
 
 }
 
-impl WindowHandler for GameWindowHandler {
-    fn on_start(&mut self, helper: &mut WindowHelper, info: WindowStartupInfo) {
+// speedy2d drops the handler when the window is closed, which is our chance to autosave
+impl Drop for GameWindowHandler {
+    fn drop(&mut self) {
+        if let Err(err) = savegame::autosave(&self.chess, &self.pgn_tags) {
+            log::warn!("Could not autosave game: {err}");
+        }
+    }
+}
+
+impl WindowHandler<EngineReport> for GameWindowHandler {
+    fn on_start(&mut self, _helper: &mut WindowHelper<EngineReport>, _info: WindowStartupInfo) {
         //log::info!("Got on_start callback: {:?}", info);
+
+        // The side to move starts thinking once the window is open
+        if let Some(clock) = &mut self.clock {
+            clock.start(Instant::now());
+        }
     }
 
-    fn on_resize(&mut self, _helper: &mut WindowHelper, size_pixels: UVec2) {
+    fn on_resize(&mut self, _helper: &mut WindowHelper<EngineReport>, size_pixels: UVec2) {
         //log::info!("Got on_resize callback: {:?}", size_pixels);
         self.ui.resize_window(&size_pixels);
     }
 
-    fn on_draw(&mut self, _helper: &mut WindowHelper, graphics: &mut Graphics2D) {
+    fn on_draw(&mut self, helper: &mut WindowHelper<EngineReport>, graphics: &mut Graphics2D) {
 
         // Load piece images if not already done
         // Unfortunately this cannot be done outside of the on_draw() callback due to a dependency on the graphics variable.
         self.ui.load_images(graphics);
 
+        self.update_clock();
+
+        // Let the computer think while it is its turn
+        self.start_computer_search();
+
+        // The board shows the game being played, unless an earlier position was picked from the move list
+        self.ui.update_move_list(&self.chess);
+        let history_position = self.ui.get_viewed_ply().map(|ply| self.chess.position_at_ply(ply));
+        let shown = history_position.as_ref().unwrap_or(&self.chess);
+
+        // Turn the board towards the side to move, if auto-flip is on
+        self.ui.follow_turn(shown);
+
         // Sets the background color
-        match self.chess.get_turn() {
+        match shown.get_turn() {
             PieceColor::BLACK => graphics.clear_screen( Color::from_int_rgb(30, 16, 16) ),
             PieceColor::WHITE => graphics.clear_screen( Color::from_int_rgb(157, 157, 157) ),
         };
 
         // draw the chess board
-        self.ui.draw_chessboard(&self.chess, graphics);
+        self.ui.draw_chessboard(shown, graphics);
+
+        // draw the move list beside the board
+        self.ui.draw_move_list(graphics);
+
+        // frame the board while it shows an earlier position
+        if history_position.is_some() {
+            self.ui.draw_history_frame(graphics);
+        }
+
+        // draw the rank and file labels
+        self.ui.draw_coordinates(graphics);
 
         // draw the hovered square (yellow)
         if let Some(sq) = self.ui.get_hovered_square() {
@@ -62,22 +245,196 @@ impl WindowHandler for GameWindowHandler {
             self.ui.draw_selected_piece_square(sq, graphics);
         }
 
+        // mark the squares the selected piece can move to
+        self.ui.draw_move_hints(&self.chess, graphics);
+
+        // draw the piece being dragged under the mouse
+        self.ui.draw_dragged_piece(&self.chess, graphics);
+
+        // draw the promotion picker on top of everything while waiting for a piece choice
+        if let Some((_, promotion_square)) = self.ui.get_pending_promotion() {
+            self.ui.draw_promotion_picker(promotion_square, self.chess.get_turn(), graphics);
+        }
+
+        // draw the game-over overlay once the game has ended
+        if shown.get_status().is_over() {
+            self.ui.draw_game_over_overlay(shown.get_status(), graphics);
+        }
+
+        // draw the move being typed, if any
+        if let Some(typed_move) = self.ui.get_typed_move() {
+            self.ui.draw_move_prompt(typed_move, graphics);
+        }
+
+        // draw both clocks, and keep redrawing while one of them is running
+        if let Some(clock) = &self.clock {
+            let now = Instant::now();
+            for color in [PieceColor::WHITE, PieceColor::BLACK] {
+                let running = clock.is_running() && clock.get_turn() == color;
+                self.ui.draw_clock(color, clock.remaining(color, now), running, graphics);
+            }
+
+            if clock.is_running() {
+                helper.request_redraw();
+            }
+        }
+
     }
 
-    fn on_mouse_move(&mut self, helper: &mut WindowHelper, position: Vec2) {
+    fn on_mouse_move(&mut self, helper: &mut WindowHelper<EngineReport>, position: Vec2) {
         self.ui.set_hovered_square(&position);
 
         helper.request_redraw();
     }
 
-    fn on_mouse_button_down(&mut self, helper: &mut WindowHelper, button: MouseButton) {
+    fn on_mouse_button_down(&mut self, helper: &mut WindowHelper<EngineReport>, _button: MouseButton) {
         //log::info!("{:?}", button);
-        self.ui.mouse_clicked(&mut self.chess);
+        let board_enabled = !self.is_computer_turn();
+        self.ui.mouse_pressed(&mut self.chess, board_enabled);
         helper.request_redraw();
     }
 
-    fn on_mouse_button_up(&mut self, helper: &mut WindowHelper, button: MouseButton) {
+    fn on_mouse_button_up(&mut self, helper: &mut WindowHelper<EngineReport>, _button: MouseButton) {
         //log::info!("{:?}", button);
+        self.ui.mouse_released(&mut self.chess);
+        self.update_clock();
         helper.request_redraw();
     }
+
+    fn on_key_down(&mut self, helper: &mut WindowHelper<EngineReport>, virtual_key_code: Option<VirtualKeyCode>, _scancode: KeyScancode) {
+        // While the move prompt is open, keys type the move instead of acting as shortcuts
+        if self.ui.get_typed_move().is_some() {
+            match virtual_key_code {
+                Some(VirtualKeyCode::Return) if self.is_computer_turn() => log::info!("Wait for the computer to move"),
+                Some(VirtualKeyCode::Return)    => {
+                    self.ui.submit_typed_move(&mut self.chess);
+                    self.update_clock();
+                },
+                Some(VirtualKeyCode::Escape)    => self.ui.close_move_prompt(),
+                Some(VirtualKeyCode::Backspace) => self.ui.erase_char(),
+                _ => {},
+            };
+
+            helper.request_redraw();
+            return;
+        }
+
+        match virtual_key_code {
+            // Enter: open the move prompt to type a move in SAN, e.g. Nf3, then Enter again to play it
+            Some(VirtualKeyCode::Return) => {
+                self.ui.show_live_position();
+                self.ui.open_move_prompt();
+            },
+            // M: make the computer move now, with the best move it has found so far
+            Some(VirtualKeyCode::M) => self.engine.stop(),
+            // Left, Right, Home and End: step through the positions of the game without changing it.
+            // Stepping past the last move, or End, goes back to the game being played.
+            Some(VirtualKeyCode::Left)  => self.ui.view_previous_move(),
+            Some(VirtualKeyCode::Right) => self.ui.view_next_move(),
+            Some(VirtualKeyCode::Home)  => self.ui.view_first_move(),
+            Some(VirtualKeyCode::End)   => self.ui.show_live_position(),
+            // D: claim a draw by the fifty-move rule or threefold repetition
+            Some(VirtualKeyCode::D) => {
+                self.ui.show_live_position();
+                match self.chess.claim_draw() {
+                    true  => log::info!("Draw claimed: {:?}", self.chess.get_status()),
+                    false => log::info!("No draw can be claimed in this position"),
+                };
+            },
+            // Ctrl+Z or Backspace: take back the last move
+            Some(VirtualKeyCode::Z) if self.is_command_held() && self.modifiers.shift() => self.redo(),
+            Some(VirtualKeyCode::Z) if self.is_command_held() => self.takeback(),
+            Some(VirtualKeyCode::Backspace) => self.takeback(),
+            // Ctrl+Y or Ctrl+Shift+Z: play the last move taken back again
+            Some(VirtualKeyCode::Y) if self.is_command_held() => self.redo(),
+            // P: print the current position as FEN, for bug reports and sharing positions
+            Some(VirtualKeyCode::P) => {
+                let fen = self.chess.to_fen();
+                println!("{fen}");
+                log::info!("FEN: {fen}");
+            },
+            // S: save the game
+            Some(VirtualKeyCode::S) => {
+                match savegame::save_game(SAVE_PATH, &self.chess, &self.pgn_tags) {
+                    Ok(())   => log::info!("Saved game to {SAVE_PATH}"),
+                    Err(err) => log::warn!("Could not save game to {SAVE_PATH}: {err}"),
+                };
+            },
+            // L: load the saved game, replacing the current one
+            Some(VirtualKeyCode::L) => {
+                match savegame::load_game(SAVE_PATH) {
+                    Ok(game) => {
+                        self.cancel_computer_search();
+                        self.ui.show_live_position();
+                        self.chess    = game.chess;
+                        self.pgn_tags = game.tags;
+                        self.ui.clear_selection();
+
+                        // The loaded game starts on a fresh clock
+                        if let Some(clock) = &mut self.clock {
+                            *clock = ChessClock::new(clock.get_time_control().clone(), self.chess.get_turn());
+                            clock.start(Instant::now());
+                        }
+                        log::info!("Loaded game from {SAVE_PATH}");
+                    },
+                    Err(err) => log::warn!("Could not load game from {SAVE_PATH}: {err}"),
+                };
+            },
+            // F: turn the board around. This also switches auto-flip off, which would turn it straight back.
+            Some(VirtualKeyCode::F) => {
+                self.ui.set_auto_flip(false);
+                self.ui.flip_board();
+            },
+            // A: turn auto-flip on or off, which keeps the side to move at the bottom for pass-and-play
+            Some(VirtualKeyCode::A) => {
+                self.ui.set_auto_flip(!self.ui.is_auto_flip());
+                log::info!("Auto-flip {}", if self.ui.is_auto_flip() { "on" } else { "off" });
+            },
+            // C: move the rank and file labels between the margin and the edge squares, or hide them
+            Some(VirtualKeyCode::C) => {
+                let placement = self.ui.cycle_coordinate_placement();
+                log::info!("Coordinates: {placement:?}");
+            },
+            // H: turn the legal move hints on or off, e.g. for practice games
+            Some(VirtualKeyCode::H) => {
+                let shown = self.ui.toggle_move_hints();
+                log::info!("Move hints {}", if shown { "on" } else { "off" });
+            },
+            // E: export the game so far as PGN
+            Some(VirtualKeyCode::E) => {
+                let pgn = self.chess.to_pgn(&self.pgn_tags);
+                println!("{pgn}");
+                log::info!("PGN:\n{pgn}");
+            },
+            _ => {},
+        };
+
+        helper.request_redraw();
+    }
+
+    fn on_user_event(&mut self, helper: &mut WindowHelper<EngineReport>, report: EngineReport) {
+        match report {
+            EngineReport::Progress { search_id, result } if Some(search_id) == self.computer_search => {
+                let pv: Vec<String> = result.pv.iter().map(ToString::to_string).collect();
+                log::info!("Computer thinking: depth {} score {} nodes {} pv {}", result.depth, result.score, result.nodes, pv.join(" "));
+            },
+            EngineReport::BestMove { search_id, result } if Some(search_id) == self.computer_search => {
+                self.play_computer_move(result);
+                helper.request_redraw();
+            },
+            // Reports of searches that were cancelled
+            _ => {},
+        };
+    }
+
+    fn on_keyboard_modifiers_changed(&mut self, _helper: &mut WindowHelper<EngineReport>, state: ModifiersState) {
+        self.modifiers = state;
+    }
+
+    fn on_keyboard_char(&mut self, helper: &mut WindowHelper<EngineReport>, unicode_codepoint: char) {
+        if self.ui.get_typed_move().is_some() && !unicode_codepoint.is_control() {
+            self.ui.type_char(unicode_codepoint);
+            helper.request_redraw();
+        }
+    }
 }
\ No newline at end of file
@@ -1,29 +1,206 @@
+use std::io::{self, BufRead, Write};
+
 use speedy2d::Window;
+use speedy2d::window::{WindowCreationOptions, WindowPosition, WindowSize};
+use speedy2d::dimen::UVec2;
 
 mod chess;
 mod gamewindowhandler;
 mod uibundle;
 mod renderer;
 mod userinputhandler;
+mod savegame;
+mod theme;
+mod movelist;
+mod clock;
+mod engine;
+mod uci;
 
 use crate::gamewindowhandler::GameWindowHandler;
 use crate::uibundle::UIBundle;
-use crate::chess::Chess;
+use crate::chess::{Chess, PgnGame, PieceColor, STARTING_FEN};
+use crate::savegame::AUTOSAVE_PATH;
+use crate::clock::TimeControl;
+use crate::engine::{EngineWorker, EngineReport};
 
 const WINDOW_HEIGHT_PX:    u32 = 800;
-const WINDOW_WIDTH_PX:     u32 = 800;
+const WINDOW_WIDTH_PX:     u32 = 1050;
 
 const GAMEVIEW_HEIGHT_PX: u32 = 750;
 const GAMEVIEW_WIDTH_PX:  u32 = 750;
 
+const PANEL_WIDTH_PX:     u32 = 225;
+
 fn main() {
 
     env_logger::init();
 
-    let window        = Window::new_centered("Chess", (WINDOW_WIDTH_PX, WINDOW_HEIGHT_PX)).unwrap();
-    let ui            = UIBundle::new(WINDOW_WIDTH_PX, WINDOW_HEIGHT_PX, GAMEVIEW_WIDTH_PX, GAMEVIEW_HEIGHT_PX);
-    let chess         = Chess::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR");
+    // Options start with --, anything else is a subcommand and its arguments
+    let (options, args): (Vec<String>, Vec<String>) = std::env::args().skip(1).partition(|arg| arg.starts_with("--"));
+
+    let mut orientation = PieceColor::WHITE;
+    let mut auto_flip   = false;
+    let mut time_control = None;
+    let mut vs_computer  = false;
+    let mut uci_mode     = false;
+
+    for option in &options {
+        match option.as_str() {
+            // Play against the clock, e.g. --clock=5+3 or --clock=40/90+30,30+30
+            clock if clock.starts_with("--clock=") => {
+                match TimeControl::parse(&clock["--clock=".len()..]) {
+                    Ok(control) => time_control = Some(control),
+                    Err(err)    => {
+                        eprintln!("{option}: {err}");
+                        std::process::exit(2);
+                    },
+                };
+            },
+            // Play from black's side, with black at the bottom of the board
+            "--black"     => orientation = PieceColor::BLACK,
+            // Keep the side to move at the bottom, for pass-and-play
+            "--auto-flip" => auto_flip = true,
+            // Play against the computer, which takes the side at the top of the board
+            "--computer"  => vs_computer = true,
+            // Run the engine for a chess GUI over the Universal Chess Interface, without a window
+            "--uci"       => uci_mode = true,
+            _ => {
+                eprintln!("unknown option {option}, expected --black, --auto-flip, --computer, --uci or --clock=<time control>");
+                std::process::exit(2);
+            },
+        };
+    }
+
+    if uci_mode {
+        uci::run();
+        return;
+    }
+
+    let game = match args.first().map(String::as_str) {
+        Some("perft") => {
+            run_perft(&args[1..]);
+            return;
+        },
+        Some("open") => open_pgn(&args[1..]),
+        _ => resume_autosave().unwrap_or_else(|| PgnGame {
+            tags:  Vec::new(),
+            chess: Chess::new(STARTING_FEN).expect("Starting position FEN is valid"),
+        }),
+    };
+
+    let computer = vs_computer.then(|| orientation.opposite());
+
+    let window_options = WindowCreationOptions::new_windowed(
+        WindowSize::PhysicalPixels(UVec2::new(WINDOW_WIDTH_PX, WINDOW_HEIGHT_PX)),
+        Some(WindowPosition::Center),
+    );
+    let window        = Window::<EngineReport>::new_with_user_events("Chess", window_options).unwrap();
+    let mut ui        = UIBundle::new(WINDOW_WIDTH_PX, WINDOW_HEIGHT_PX, GAMEVIEW_WIDTH_PX, GAMEVIEW_HEIGHT_PX, PANEL_WIDTH_PX);
+
+    ui.set_orientation(orientation);
+    ui.set_auto_flip(auto_flip);
+
+    // The engine hands its reports to the window's event loop, which wakes up the handler
+    let event_sender = window.create_user_event_sender();
+    let engine = EngineWorker::spawn(move |report| {
+        // Fails only once the window is gone, when nobody is waiting for the move any more
+        let _ = event_sender.send_event(report);
+    });
+
+    window.run_loop( GameWindowHandler::new( ui, game.chess, game.tags, time_control, computer, engine ) );
+
+}
+
+// If the window was closed during a game, asks on the terminal whether to carry on with it.
+fn resume_autosave() -> Option<PgnGame> {
+    let game = match savegame::load_game(AUTOSAVE_PATH) {
+        Ok(game) => game,
+        Err(savegame::SaveGameError::Io(err)) if err.kind() == io::ErrorKind::NotFound => return None,
+        Err(err) => {
+            log::warn!("Ignoring unreadable autosave {AUTOSAVE_PATH}: {err}");
+            return None;
+        },
+    };
+
+    print!("Resume your unfinished game ({} moves played)? [Y/n] ", game.chess.get_played_moves().len());
+    io::stdout().flush().ok()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).ok()?;
+
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "" | "y" | "yes" => Some(game),
+        _                => None,
+    }
+}
+
+// chess open <file.pgn> [game number]
+// Picks one game out of a PGN file to continue from, the first one unless a number is given.
+fn open_pgn(args: &[String]) -> PgnGame {
+    let usage = || -> ! {
+        eprintln!("usage: chess open <file.pgn> [game number]");
+        std::process::exit(2);
+    };
+
+    let (path, number) = match args {
+        [path]         => (path, 1),
+        [path, number] => (path, number.parse::<usize>().unwrap_or_else(|_| usage())),
+        _              => usage(),
+    };
+
+    let text = std::fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("cannot read {path}: {err}");
+        std::process::exit(1);
+    });
+
+    let mut games = Chess::from_pgn(&text).unwrap_or_else(|err| {
+        eprintln!("{path}: {err}");
+        std::process::exit(1);
+    });
+
+    if number == 0 || number > games.len() {
+        eprintln!("{path} has {} games, there is no game {number}", games.len());
+        std::process::exit(1);
+    }
+
+    games.swap_remove(number - 1)
+}
+
+// chess perft <fen> <depth>
+// Prints the node count below each legal move, then the total. The FEN may be given quoted or as separate arguments.
+fn run_perft(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!("usage: chess perft <fen> <depth>");
+        std::process::exit(2);
+    };
+
+    let (depth, fen) = match args.split_last() {
+        Some((depth, fen)) if !fen.is_empty() => (depth, fen.join(" ")),
+        _ => usage(),
+    };
+
+    let depth: u32 = match depth.parse() {
+        Ok(depth) => depth,
+        Err(_)    => usage(),
+    };
+
+    let mut chess = match Chess::new(&fen) {
+        Ok(chess) => chess,
+        Err(err)  => {
+            eprintln!("invalid FEN: {err}");
+            std::process::exit(1);
+        },
+    };
 
-    window.run_loop( GameWindowHandler::new( ui, chess ) );
+    let counts = chess.divide(depth);
+    for (mv, nodes) in &counts {
+        println!("{mv}: {nodes}");
+    }
 
-}
\ No newline at end of file
+    let total: u64 = match depth {
+        0 => 1,
+        _ => counts.iter().map(|(_, nodes)| nodes).sum(),
+    };
+    println!();
+    println!("Nodes searched: {total}");
+}
@@ -0,0 +1,155 @@
+use speedy2d::shape::{Rectangle, URect};
+use speedy2d::dimen::Vec2;
+
+use crate::chess::{Chess, Move, PieceColor};
+
+// Rows of moves the panel shows at once, below its header row
+pub const MOVE_LIST_ROWS: usize = 29;
+
+// Share of the panel's width taken by the move number, white's move and black's move
+const COLUMN_WIDTHS: [f32; 3] = [0.2, 0.4, 0.4];
+
+// The moves of the game in SAN, laid out in rows of move number, white's move and black's move.
+// Converting moves to SAN means replaying the game, so it is only redone when the moves change.
+pub struct MoveList {
+    moves: Vec<Move>,
+    san: Vec<String>,
+    first_move_number: u32,
+    // A game set up with black to move leaves white's half of the first row empty
+    starts_with_black: bool,
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        MoveList {
+            moves: Vec::new(),
+            san: Vec::new(),
+            first_move_number: 1,
+            starts_with_black: false,
+        }
+    }
+
+    pub fn update(&mut self, chess: &Chess) {
+        let moves = chess.get_played_moves();
+        if moves == self.moves { return; }
+
+        let start = chess.position_at_ply(0);
+        self.first_move_number = start.get_fullmove_number();
+        self.starts_with_black = start.get_turn() == PieceColor::BLACK;
+        self.san = chess.played_moves_in_san();
+        self.moves = moves;
+    }
+
+    pub fn len(&self) -> usize {
+        self.san.len()
+    }
+
+    pub fn get_san(&self, index: usize) -> &str {
+        &self.san[index]
+    }
+
+    // The row a move is in, and 0 for white's half of it or 1 for black's
+    pub fn row_and_half(&self, index: usize) -> (usize, usize) {
+        let slot = index + self.starts_with_black as usize;
+        (slot / 2, slot % 2)
+    }
+
+    pub fn row_count(&self) -> usize {
+        match self.san.len() {
+            0   => 0,
+            len => self.row_and_half(len - 1).0 + 1,
+        }
+    }
+
+    pub fn move_number(&self, row: usize) -> u32 {
+        self.first_move_number + row as u32
+    }
+
+    // The move in the given half of a row, if there is one
+    pub fn index_at(&self, row: usize, half: usize) -> Option<usize> {
+        let slot = row * 2 + half;
+        let index = slot.checked_sub(self.starts_with_black as usize)?;
+
+        match index < self.san.len() {
+            true  => Some(index),
+            false => None,
+        }
+    }
+
+    // The first row to show so that the row holding focus_index is on screen.
+    // With the focus on the latest move this keeps the end of the list in view as the game goes on.
+    pub fn first_visible_row(&self, focus_index: Option<usize>) -> usize {
+        let focus_row = focus_index.map_or(0, |index| self.row_and_half(index).0);
+        (focus_row + 1).saturating_sub(MOVE_LIST_ROWS)
+    }
+
+    pub fn row_height(panel_rect: &URect) -> f32 {
+        (panel_rect.bottom_right().y - panel_rect.top_left().y) as f32 / (MOVE_LIST_ROWS + 1) as f32
+    }
+
+    // Where a cell of the panel is drawn. Visible row 0 is the header, column 0 the move number,
+    // 1 white's move and 2 black's move.
+    pub fn cell_rect(panel_rect: &URect, visible_row: usize, column: usize) -> Rectangle<f32> {
+        let panel_width = (panel_rect.bottom_right().x - panel_rect.top_left().x) as f32;
+        let row_height  = Self::row_height(panel_rect);
+
+        let left  = panel_rect.top_left().x as f32 + COLUMN_WIDTHS[..column].iter().sum::<f32>() * panel_width;
+        let top   = panel_rect.top_left().y as f32 + visible_row as f32 * row_height;
+        let width = COLUMN_WIDTHS[column] * panel_width;
+
+        Rectangle::new(Vec2::new(left, top), Vec2::new(left + width, top + row_height))
+    }
+
+    // The move drawn under position, if any, with the rows scrolled to show focus_index.
+    pub fn index_at_position(&self, panel_rect: &URect, focus_index: Option<usize>, position: Vec2) -> Option<usize> {
+        let first_row = self.first_visible_row(focus_index);
+
+        for visible_row in 1..=MOVE_LIST_ROWS {
+            for half in 0..2 {
+                if Self::cell_rect(panel_rect, visible_row, half + 1).contains(position) {
+                    return self.index_at(first_row + visible_row - 1, half);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MoveList;
+    use crate::chess::{play_san, Chess, STARTING_FEN};
+
+    fn move_list_after(fen: &str, moves: &[&str]) -> MoveList {
+        let mut chess = Chess::new(fen).unwrap();
+        play_san(&mut chess, moves);
+
+        let mut move_list = MoveList::new();
+        move_list.update(&chess);
+        move_list
+    }
+
+    #[test]
+    fn lays_out_moves_in_numbered_pairs() {
+        let move_list = move_list_after(STARTING_FEN, &["e4", "e5", "Nf3"]);
+
+        assert_eq!(move_list.row_count(), 2);
+        assert_eq!(move_list.move_number(1), 2);
+        assert_eq!(move_list.row_and_half(2), (1, 0));
+        assert_eq!(move_list.index_at(0, 1), Some(1));
+        assert_eq!(move_list.index_at(1, 1), None);
+        assert_eq!(move_list.get_san(2), "Nf3");
+    }
+
+    #[test]
+    fn leaves_white_half_empty_when_black_moves_first() {
+        let move_list = move_list_after("4k3/8/8/8/8/8/8/R3K3 b - - 0 40", &["Kd7", "Ra7+"]);
+
+        assert_eq!(move_list.move_number(0), 40);
+        assert_eq!(move_list.index_at(0, 0), None);
+        assert_eq!(move_list.index_at(0, 1), Some(0));
+        assert_eq!(move_list.row_and_half(1), (1, 0));
+        assert_eq!(move_list.get_san(1), "Ra7+");
+    }
+}
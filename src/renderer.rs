@@ -1,25 +1,74 @@
 
 use std::collections::HashMap;
+use std::time::Duration;
 
 use speedy2d::image::{ImageHandle, ImageSmoothingMode};
 use speedy2d::shape::{Rectangle, URect};
-use speedy2d::dimen::{Vec2, UVec2};
+use speedy2d::dimen::Vec2;
 use speedy2d::color::Color;
+use speedy2d::font::{Font, TextLayout, TextOptions};
 use speedy2d::Graphics2D;
 
+use std::f32::consts::TAU;
+
 use crate::chess::Chess;
-use crate::chess::{PieceColor, PieceName};
+use crate::chess::{PieceColor, PieceName, GameStatus, GameResult, DrawReason};
+use crate::theme::Theme;
+use crate::movelist::{MoveList, MOVE_LIST_ROWS};
+use crate::clock;
+
+// Thickness of the frame drawn around the board while looking at an earlier position
+const HISTORY_FRAME_WIDTH: f32 = 4.0;
+
+const FONT_PATH: &str = "fonts/DejaVuSans.ttf";
+
+// Where the rank and file labels go
+#[derive(Debug)]
+#[derive(Eq)]
+#[derive(PartialEq)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub enum CoordinatePlacement {
+    // In the margin around the board
+    Margin,
+    // In the corners of the squares along the bottom and left edges
+    InsideSquares,
+    Hidden,
+}
+
+impl CoordinatePlacement {
+    // The placement after this one, for cycling through them with a key
+    pub fn next(self) -> Self {
+        match self {
+            CoordinatePlacement::Margin        => CoordinatePlacement::InsideSquares,
+            CoordinatePlacement::InsideSquares => CoordinatePlacement::Hidden,
+            CoordinatePlacement::Hidden        => CoordinatePlacement::Margin,
+        }
+    }
+}
+
+// Number of straight pieces rings and glows are built from
+const RING_SEGMENTS: usize = 48;
 
 #[derive(Debug)]
 pub struct Renderer {
     piece_images: Option<HashMap<PieceName, [ImageHandle; 2]>>,
+    font: Font,
+    theme: Theme,
+    // The side whose pieces start at the bottom of the board
+    orientation: PieceColor,
 }
 
 impl Renderer {
     pub fn new() -> Self {
 
+        let font_bytes = std::fs::read(FONT_PATH).unwrap_or_else(|_| panic!("Failed to load font {}", FONT_PATH));
+
         Renderer {
             piece_images: None,
+            font: Font::new(&font_bytes).unwrap_or_else(|_| panic!("Failed to parse font {}", FONT_PATH)),
+            theme: Theme::default(),
+            orientation: PieceColor::WHITE,
         }
     }
 
@@ -56,59 +105,380 @@ impl Renderer {
 
 
         [
-            graphics.create_image_from_file_path(None, ImageSmoothingMode::NearestNeighbor, path_light.clone()).expect( &format!("Failed to load image for {}", path_light).to_owned()),
-            graphics.create_image_from_file_path(None, ImageSmoothingMode::NearestNeighbor,  path_dark.clone()).expect( &format!("Failed to load image for {}", path_dark).to_owned()),
+            graphics.create_image_from_file_path(None, ImageSmoothingMode::NearestNeighbor, path_light.clone()).unwrap_or_else(|_| panic!("Failed to load image for {}", path_light)),
+            graphics.create_image_from_file_path(None, ImageSmoothingMode::NearestNeighbor,  path_dark.clone()).unwrap_or_else(|_| panic!("Failed to load image for {}", path_dark)),
         ]
     } 
 
-    pub fn draw_chessboard(&self, gameview_rect: &URect, chess: &Chess, graphics: &mut Graphics2D) {
+    pub fn draw_chessboard(&self, gameview_rect: &URect, chess: &Chess, hidden_square: Option<usize>, graphics: &mut Graphics2D) {
 
         for square in 0..=63 {
+            self.draw_square(gameview_rect, square, graphics);
+        }
 
-            Self::draw_square(gameview_rect, square, graphics);
+        // Highlights go between the squares and the pieces, so they do not cover the pieces
+        if let Some(last_move) = chess.get_last_move() {
+            self.draw_last_move(gameview_rect, last_move.src, last_move.dst, graphics);
+        }
+
+        if chess.is_in_check() {
+            self.draw_check_highlight(gameview_rect, chess.get_king_square(chess.get_turn()), graphics);
+        }
 
+        for square in 0..=63 {
             if let Some(piece) = chess.get_piece_at_square(square) {
-                self.draw_piece(gameview_rect, square, piece, graphics);
+                if hidden_square != Some(square) {
+                    self.draw_piece(gameview_rect, square, piece, graphics);
+                }
             }
+        }
+    }
 
+    fn draw_last_move(&self, gameview_rect: &URect, src: usize, dst: usize, graphics: &mut Graphics2D) {
+        for square in [src, dst] {
+            let rect = self.make_rect_for_square(gameview_rect, square);
+            graphics.draw_rectangle(&rect, self.theme.last_move);
         }
     }
 
+    // A glow under the king, strongest in the middle of its square and fading out towards the edges.
+    fn draw_check_highlight(&self, gameview_rect: &URect, king_square: usize, graphics: &mut Graphics2D) {
+        let rect = self.make_rect_for_square(gameview_rect, king_square);
+        let center = (rect.top_left() + rect.bottom_right()) / 2.0;
+        let radius = Self::calc_square_length(gameview_rect) as f32 / 2.0;
+
+        let faded = Color::from_rgba(self.theme.check.r(), self.theme.check.g(), self.theme.check.b(), 0.0);
+
+        for segment in 0..RING_SEGMENTS {
+            graphics.draw_triangle_three_color(
+                [center, Self::point_on_circle(center, radius, segment), Self::point_on_circle(center, radius, segment + 1)],
+                [self.theme.check, faded, faded],
+            );
+        }
+    }
+
+    // Draws a piece the size of a square, centered on the mouse.
+    pub fn draw_dragged_piece(&self, gameview_rect: &URect, piece: u8, mouse_position: Vec2, graphics: &mut Graphics2D) {
+        let half_square = Self::calc_square_length(gameview_rect) as f32 / 2.0;
+        let rect = Rectangle::new(mouse_position - Vec2::new(half_square, half_square), mouse_position + Vec2::new(half_square, half_square));
+
+        graphics.draw_rectangle_image(&rect, self.image_for_piece(piece));
+    }
+
     fn draw_piece(&self, gameview_rect: &URect, square: usize, piece: u8, graphics: &mut Graphics2D) {
-        let rect = Self::make_rect_for_square(gameview_rect, square);
+        let rect = self.make_rect_for_square(gameview_rect, square);
+        graphics.draw_rectangle_image(&rect, self.image_for_piece(piece));
+    }
+
+    fn image_for_piece(&self, piece: u8) -> &ImageHandle {
         let name  = Chess::get_name_for_piece(piece);
 
-        let imagehandle = match Chess::get_color_for_piece(piece) {
+        match Chess::get_color_for_piece(piece) {
             PieceColor::WHITE => &self.piece_images.as_ref().unwrap().get(&name).unwrap()[0],
             PieceColor::BLACK => &self.piece_images.as_ref().unwrap().get(&name).unwrap()[1],
-        };
-
-        graphics.draw_rectangle_image(&rect, imagehandle);
+        }
     }
 
-    fn draw_square(gameview_rect: &URect, square: usize, graphics: &mut Graphics2D) {
-        let rect = Self::make_rect_for_square(gameview_rect, square);
+    fn draw_square(&self, gameview_rect: &URect, square: usize, graphics: &mut Graphics2D) {
+        let rect = self.make_rect_for_square(gameview_rect, square);
 
         // Gets us a checkerboard pattern
-        let rect_color = match (square + square / 8 % 2 ) % 2 {
-            0 => Color::from_int_rgb(253, 245, 245), /* light */
-            _ => Color::from_int_rgb(36, 78, 36),    /* dark */
+        let rect_color = match Self::is_light_square(square) {
+            true  => self.theme.light_square,
+            false => self.theme.dark_square,
         };
 
         graphics.draw_rectangle(&rect, rect_color);
     }
 
     // Only used for hovered square right now
-    pub fn draw_hovered_square(gameview_rect: &URect, square: usize, graphics: &mut Graphics2D) {
-        let rect = Self::make_rect_for_square(gameview_rect, square);
-        let square_color = Color::from_int_rgba(255, 255, 0, 127);
-        graphics.draw_rectangle(&rect, square_color);
+    pub fn draw_hovered_square(&self, gameview_rect: &URect, square: usize, graphics: &mut Graphics2D) {
+        let rect = self.make_rect_for_square(gameview_rect, square);
+        graphics.draw_rectangle(&rect, self.theme.hovered_square);
+    }
+
+    // Marks where the selected piece can go: a dot on each empty square and a ring around each piece it can capture.
+    pub fn draw_move_hints(&self, gameview_rect: &URect, destinations: &[usize], chess: &Chess, graphics: &mut Graphics2D) {
+        let square_length = Self::calc_square_length(gameview_rect) as f32;
+        let hint_color = self.theme.move_hint;
+
+        for &square in destinations {
+            let rect = self.make_rect_for_square(gameview_rect, square);
+            let center = (rect.top_left() + rect.bottom_right()) / 2.0;
+
+            match chess.is_square_empty(square) {
+                true  => graphics.draw_circle(center, square_length * 0.16, hint_color),
+                false => Self::draw_ring(center, square_length * 0.5, square_length * 0.42, hint_color, graphics),
+            };
+        }
     }
 
-    pub fn draw_selected_piece_square(gameview_rect: &URect, square: usize, graphics: &mut Graphics2D) {
-        let rect = Self::make_rect_for_square(gameview_rect, square);
-        let square_color = Color::from_int_rgba(166, 22, 43, 80);
-        graphics.draw_rectangle(&rect, square_color);
+    fn draw_ring(center: Vec2, outer_radius: f32, inner_radius: f32, color: Color, graphics: &mut Graphics2D) {
+        let point = |radius: f32, segment: usize| Self::point_on_circle(center, radius, segment);
+
+        for segment in 0..RING_SEGMENTS {
+            graphics.draw_quad([
+                point(outer_radius, segment),
+                point(outer_radius, segment + 1),
+                point(inner_radius, segment + 1),
+                point(inner_radius, segment),
+            ], color);
+        }
+    }
+
+    // The point at the start of the given segment when a circle is split into RING_SEGMENTS pieces.
+    fn point_on_circle(center: Vec2, radius: f32, segment: usize) -> Vec2 {
+        let angle = TAU * segment as f32 / RING_SEGMENTS as f32;
+        center + Vec2::new(angle.cos(), angle.sin()) * radius
+    }
+
+    pub fn draw_selected_piece_square(&self, gameview_rect: &URect, square: usize, graphics: &mut Graphics2D) {
+        let rect = self.make_rect_for_square(gameview_rect, square);
+        graphics.draw_rectangle(&rect, self.theme.selected_square);
+    }
+
+    // Dims the board and shows the pieces a pawn can promote to, one per picker square.
+    pub fn draw_promotion_picker(&self, gameview_rect: &URect, squares: &[usize; 4], choices: &[PieceName; 4], color: PieceColor, graphics: &mut Graphics2D) {
+        let gameview = Rectangle::new(gameview_rect.top_left().into_f32(), gameview_rect.bottom_right().into_f32());
+        graphics.draw_rectangle(&gameview, Color::from_int_rgba(0, 0, 0, 140));
+
+        for (&square, &name) in squares.iter().zip(choices.iter()) {
+            let rect = self.make_rect_for_square(gameview_rect, square);
+            graphics.draw_rectangle(&rect, Color::from_int_rgb(224, 224, 224));
+            self.draw_piece(gameview_rect, square, name as u8 | color as u8, graphics);
+        }
+    }
+
+    // Dims the board and writes the outcome of the game across it.
+    pub fn draw_game_over_overlay(&self, gameview_rect: &URect, status: GameStatus, graphics: &mut Graphics2D) {
+        let headline = match status {
+            GameStatus::Checkmate(_) => "Checkmate",
+            GameStatus::Stalemate    => "Stalemate",
+            GameStatus::TimeForfeit(_) => "Out of time",
+            GameStatus::Draw(_)      => "Draw",
+            GameStatus::Ongoing      => return,
+        };
+
+        let subline = match (status, status.result()) {
+            (GameStatus::Draw(DrawReason::FiftyMoveRule), _)        => "Fifty-move rule",
+            (GameStatus::Draw(DrawReason::ThreefoldRepetition), _)  => "Threefold repetition",
+            (GameStatus::Draw(DrawReason::SeventyFiveMoveRule), _)  => "Seventy-five-move rule",
+            (GameStatus::Draw(DrawReason::FivefoldRepetition), _)   => "Fivefold repetition",
+            (GameStatus::Draw(DrawReason::InsufficientMaterial), _) => "Insufficient material",
+            (GameStatus::Draw(DrawReason::TimeoutVsInsufficientMaterial), _) => "Timeout vs insufficient material",
+            (_, Some(GameResult::WhiteWins)) => "White wins",
+            (_, Some(GameResult::BlackWins)) => "Black wins",
+            (_, Some(GameResult::Draw))      => "Draw",
+            (_, None)                        => return,
+        };
+
+        let gameview = Rectangle::new(gameview_rect.top_left().into_f32(), gameview_rect.bottom_right().into_f32());
+        graphics.draw_rectangle(&gameview, Color::from_int_rgba(0, 0, 0, 160));
+
+        let square_length = Self::calc_square_length(gameview_rect) as f32;
+        let headline_text = self.font.layout_text(headline, square_length, TextOptions::new());
+        let subline_text  = self.font.layout_text(subline, square_length / 2.0, TextOptions::new());
+
+        // Center both lines of text in the middle of the board
+        let center = (gameview.top_left() + gameview.bottom_right()) / 2.0;
+        let headline_pos = Vec2::new(center.x - headline_text.width() / 2.0, center.y - headline_text.height());
+        let subline_pos  = Vec2::new(center.x - subline_text.width()  / 2.0, center.y);
+
+        graphics.draw_text(headline_pos, Color::WHITE, &headline_text);
+        graphics.draw_text(subline_pos,  Color::WHITE, &subline_text);
+    }
+
+    // Writes the move being typed into the margin above the board.
+    pub fn draw_move_prompt(&self, window_rect: &URect, gameview_rect: &URect, typed_move: &str, graphics: &mut Graphics2D) {
+        let margin_height = (gameview_rect.top_left().y - window_rect.top_left().y) as f32;
+        let prompt = self.font.layout_text(&format!("Move: {typed_move}_"), margin_height * 0.7, TextOptions::new());
+
+        let position = Vec2::new(
+            gameview_rect.top_left().x as f32,
+            window_rect.top_left().y as f32 + (margin_height - prompt.height()) / 2.0,
+        );
+        graphics.draw_text(position, self.theme.margin_text, &prompt);
+    }
+
+    // Writes a player's remaining time in the margin beside the panel, above it for the player at the top of the board
+    // and below it for the player at the bottom. A running clock is drawn on a colored background.
+    pub fn draw_clock(&self, window_rect: &URect, panel_rect: &URect, color: PieceColor, time: Duration, running: bool, graphics: &mut Graphics2D) {
+        let at_top = color != self.orientation;
+
+        let (margin_top, margin_height) = match at_top {
+            true  => (window_rect.top_left().y, panel_rect.top_left().y - window_rect.top_left().y),
+            false => (panel_rect.bottom_right().y, window_rect.bottom_right().y - panel_rect.bottom_right().y),
+        };
+        let margin_top    = margin_top as f32;
+        let margin_height = margin_height as f32;
+
+        let text = self.font.layout_text(&clock::format_clock(time), margin_height * 0.7, TextOptions::new());
+
+        // Right-aligned with the panel
+        let position = Vec2::new(
+            panel_rect.bottom_right().x as f32 - text.width(),
+            margin_top + (margin_height - text.height()) / 2.0,
+        );
+
+        let text_color = match running {
+            true  => {
+                let padding = margin_height * 0.1;
+                let background = Rectangle::new(
+                    position - Vec2::new(padding, padding),
+                    position + Vec2::new(text.width() + padding, text.height() + padding),
+                );
+                graphics.draw_rectangle(&background, self.theme.clock_running);
+                self.theme.panel_text
+            },
+            false => self.theme.margin_text,
+        };
+
+        graphics.draw_text(position, text_color, &text);
+    }
+
+    // Writes the files a to h along the bottom of the board and the ranks 1 to 8 along its left side,
+    // in the order they appear with the board's current orientation.
+    pub fn draw_coordinates(&self, window_rect: &URect, gameview_rect: &URect, placement: CoordinatePlacement, graphics: &mut Graphics2D) {
+        if placement == CoordinatePlacement::Hidden { return; }
+
+        let square_length = Self::calc_square_length(gameview_rect) as f32;
+
+        for index in 0..8 {
+            // Bottom row of squares, left to right, and left column, top to bottom
+            let bottom_square = Self::display_square(56 + index, self.orientation);
+            let left_square   = Self::display_square(index * 8, self.orientation);
+
+            let file_label = Chess::square_name(bottom_square)[..1].to_string();
+            let rank_label = Chess::square_name(left_square)[1..].to_string();
+
+            let bottom_rect = self.make_rect_for_square(gameview_rect, bottom_square);
+            let left_rect   = self.make_rect_for_square(gameview_rect, left_square);
+
+            match placement {
+                CoordinatePlacement::Margin => {
+                    let bottom_margin = (window_rect.bottom_right().y - gameview_rect.bottom_right().y) as f32;
+                    let left_margin   = (gameview_rect.top_left().x - window_rect.top_left().x) as f32;
+                    let text_size     = bottom_margin.min(left_margin).min(square_length / 2.0) * 0.7;
+
+                    let file_text = self.font.layout_text(&file_label, text_size, TextOptions::new());
+                    let rank_text = self.font.layout_text(&rank_label, text_size, TextOptions::new());
+
+                    // Centered under each column and beside each row
+                    let file_position = Vec2::new(
+                        (bottom_rect.top_left().x + bottom_rect.bottom_right().x - file_text.width()) / 2.0,
+                        bottom_rect.bottom_right().y + (bottom_margin - file_text.height()) / 2.0,
+                    );
+                    let rank_position = Vec2::new(
+                        window_rect.top_left().x as f32 + (left_margin - rank_text.width()) / 2.0,
+                        (left_rect.top_left().y + left_rect.bottom_right().y - rank_text.height()) / 2.0,
+                    );
+
+                    graphics.draw_text(file_position, self.theme.margin_text, &file_text);
+                    graphics.draw_text(rank_position, self.theme.margin_text, &rank_text);
+                },
+                CoordinatePlacement::InsideSquares => {
+                    let text_size = square_length * 0.2;
+                    let padding   = square_length * 0.04;
+
+                    let file_text = self.font.layout_text(&file_label, text_size, TextOptions::new());
+                    let rank_text = self.font.layout_text(&rank_label, text_size, TextOptions::new());
+
+                    // Files in the bottom right corner of the bottom row, ranks in the top left corner of the left column
+                    let file_position = bottom_rect.bottom_right() - Vec2::new(file_text.width() + padding, file_text.height() + padding);
+                    let rank_position = left_rect.top_left() + Vec2::new(padding, padding);
+
+                    graphics.draw_text(file_position, self.contrasting_square_color(bottom_square), &file_text);
+                    graphics.draw_text(rank_position, self.contrasting_square_color(left_square), &rank_text);
+                },
+                CoordinatePlacement::Hidden => {},
+            };
+        }
+    }
+
+    // Numbered rows of white's and black's moves, scrolled to keep the current move in view.
+    // The header says whether the board shows the game being played or an earlier position.
+    pub fn draw_move_list(&self, panel_rect: &URect, move_list: &MoveList, current_move: Option<usize>, viewing_history: bool, graphics: &mut Graphics2D) {
+        let panel = Rectangle::new(panel_rect.top_left().into_f32(), panel_rect.bottom_right().into_f32());
+        graphics.draw_rectangle(&panel, self.theme.panel_background);
+
+        let text_size = MoveList::row_height(panel_rect) * 0.65;
+        let draw_cell_text = |text: &str, rect: &Rectangle<f32>, color: Color, graphics: &mut Graphics2D| {
+            let layout = self.font.layout_text(text, text_size, TextOptions::new());
+            let position = Vec2::new(rect.top_left().x + text_size * 0.3, rect.top_left().y + (rect.height() - layout.height()) / 2.0);
+            graphics.draw_text(position, color, &layout);
+        };
+
+        // Header, across the whole width of the panel
+        let header = Rectangle::new(*MoveList::cell_rect(panel_rect, 0, 0).top_left(), *MoveList::cell_rect(panel_rect, 0, 2).bottom_right());
+        match viewing_history {
+            true  => {
+                graphics.draw_rectangle(&header, self.theme.history);
+                draw_cell_text("Viewing history - End to return", &header, self.theme.panel_background, graphics);
+            },
+            false => draw_cell_text("Moves", &header, self.theme.panel_text, graphics),
+        };
+
+        let first_row = move_list.first_visible_row(current_move);
+        let last_row  = move_list.row_count().min(first_row + MOVE_LIST_ROWS);
+
+        for row in first_row..last_row {
+            let visible_row = row - first_row + 1;
+
+            draw_cell_text(&format!("{}.", move_list.move_number(row)), &MoveList::cell_rect(panel_rect, visible_row, 0), self.theme.panel_text, graphics);
+
+            for half in 0..2 {
+                if let Some(index) = move_list.index_at(row, half) {
+                    let rect = MoveList::cell_rect(panel_rect, visible_row, half + 1);
+
+                    if current_move == Some(index) {
+                        graphics.draw_rectangle(&rect, self.theme.current_move);
+                    }
+                    draw_cell_text(move_list.get_san(index), &rect, self.theme.panel_text, graphics);
+                }
+            }
+        }
+    }
+
+    // A frame around the board, so an earlier position is not mistaken for the game being played
+    pub fn draw_history_frame(&self, gameview_rect: &URect, graphics: &mut Graphics2D) {
+        let top_left     = gameview_rect.top_left().into_f32();
+        let bottom_right = gameview_rect.bottom_right().into_f32();
+        let top_right    = Vec2::new(bottom_right.x, top_left.y);
+        let bottom_left  = Vec2::new(top_left.x, bottom_right.y);
+
+        for (start, end) in [(top_left, top_right), (top_right, bottom_right), (bottom_right, bottom_left), (bottom_left, top_left)] {
+            graphics.draw_line(start, end, HISTORY_FRAME_WIDTH, self.theme.history);
+        }
+    }
+
+    // The color of the other kind of square, which stands out against this one
+    fn contrasting_square_color(&self, square: usize) -> Color {
+        match Self::is_light_square(square) {
+            true  => self.theme.dark_square,
+            false => self.theme.light_square,
+        }
+    }
+
+    fn is_light_square(square: usize) -> bool {
+        (square + square / 8 % 2).is_multiple_of(2)
+    }
+
+    pub fn get_orientation(&self) -> PieceColor {
+        self.orientation
+    }
+
+    pub fn set_orientation(&mut self, orientation: PieceColor) {
+        self.orientation = orientation;
+    }
+
+    // Where a square is drawn, counted like a square index from the top left of the board.
+    // With black at the bottom the board is turned around, which reverses the order of the squares.
+    // The mapping is its own inverse, so it also turns a position on screen back into a square.
+    pub fn display_square(square: usize, orientation: PieceColor) -> usize {
+        match orientation {
+            PieceColor::WHITE => square,
+            PieceColor::BLACK => 63 - square,
+        }
     }
 
     fn calc_square_length(gameview_rect: &URect) -> u32 {
@@ -118,10 +488,12 @@ impl Renderer {
         gameview_rect_width / 8
     }
 
-    fn make_rect_for_square(gameview_rect: &URect, square: usize) -> Rectangle<f32> {
+    fn make_rect_for_square(&self, gameview_rect: &URect, square: usize) -> Rectangle<f32> {
+        let display_square = Self::display_square(square, self.orientation);
+
         let rect_len = Self::calc_square_length(gameview_rect);
-        let x = gameview_rect.top_left().x as f32 + (square % 8) as f32 * rect_len as f32;
-        let y = gameview_rect.top_left().y as f32 + (square / 8) as f32 * rect_len as f32;
+        let x = gameview_rect.top_left().x as f32 + (display_square % 8) as f32 * rect_len as f32;
+        let y = gameview_rect.top_left().y as f32 + (display_square / 8) as f32 * rect_len as f32;
 
         let top_left = Vec2::new(x, y);
         let bottom_right = Vec2::new(x + rect_len as f32, y + rect_len as f32);
@@ -0,0 +1,61 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::chess::{Chess, PgnGame, PgnError};
+
+// Games are saved as PGN, so they can also be opened in other chess programs
+pub const SAVE_PATH:     &str = "saves/saved_game.pgn";
+// Written when the window closes on an unfinished game, and offered for resuming at the next start
+pub const AUTOSAVE_PATH: &str = "saves/autosave.pgn";
+
+#[derive(Debug)]
+pub enum SaveGameError {
+    Io(io::Error),
+    Pgn(PgnError),
+    // The file is valid PGN but holds no game
+    NoGame,
+}
+
+impl fmt::Display for SaveGameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveGameError::Io(err)  => write!(f, "{err}"),
+            SaveGameError::Pgn(err) => write!(f, "{err}"),
+            SaveGameError::NoGame   => write!(f, "no game in file"),
+        }
+    }
+}
+
+impl std::error::Error for SaveGameError {}
+
+pub fn save_game(path: &str, chess: &Chess, pgn_tags: &[(String, String)]) -> Result<(), SaveGameError> {
+    if let Some(directory) = Path::new(path).parent() {
+        fs::create_dir_all(directory).map_err(SaveGameError::Io)?;
+    }
+
+    fs::write(path, chess.to_pgn(pgn_tags)).map_err(SaveGameError::Io)
+}
+
+// Loads the last game in the file, which for our own save files is the only one.
+pub fn load_game(path: &str) -> Result<PgnGame, SaveGameError> {
+    let text = fs::read_to_string(path).map_err(SaveGameError::Io)?;
+    let games = Chess::from_pgn(&text).map_err(SaveGameError::Pgn)?;
+
+    games.into_iter().last().ok_or(SaveGameError::NoGame)
+}
+
+// Autosaves an unfinished game that has at least one move. Otherwise there is nothing worth resuming,
+// so any older autosave is removed instead.
+pub fn autosave(chess: &Chess, pgn_tags: &[(String, String)]) -> Result<(), SaveGameError> {
+    let is_unfinished = !chess.get_status().is_over() && !chess.get_played_moves().is_empty();
+
+    match is_unfinished {
+        true  => save_game(AUTOSAVE_PATH, chess, pgn_tags),
+        false => match fs::remove_file(AUTOSAVE_PATH) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(SaveGameError::Io(err)),
+            _ => Ok(()),
+        },
+    }
+}
@@ -0,0 +1,47 @@
+use speedy2d::color::Color;
+
+// Every color used to draw the board. The renderer draws with whichever theme is active.
+#[derive(Debug)]
+#[derive(Copy)]
+#[derive(Clone)]
+pub struct Theme {
+    pub light_square:    Color,
+    pub dark_square:     Color,
+    pub hovered_square:  Color,
+    pub selected_square: Color,
+    pub move_hint:       Color,
+    // Tint over the source and destination squares of the last move
+    pub last_move:       Color,
+    // Center color of the glow under a king in check, fading out towards the edge of its square
+    pub check:           Color,
+    // Text drawn in the margin around the board, such as coordinates
+    pub margin_text:     Color,
+    pub panel_background: Color,
+    pub panel_text:      Color,
+    // Behind the move leading to the position on the board
+    pub current_move:    Color,
+    // Marks that an earlier position is shown rather than the game being played
+    pub history:         Color,
+    // Behind the clock of the side to move while it runs
+    pub clock_running:   Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            light_square:    Color::from_int_rgb(253, 245, 245),
+            dark_square:     Color::from_int_rgb(36, 78, 36),
+            hovered_square:  Color::from_int_rgba(255, 255, 0, 127),
+            selected_square: Color::from_int_rgba(166, 22, 43, 80),
+            move_hint:       Color::from_int_rgba(20, 20, 20, 70),
+            last_move:       Color::from_int_rgba(205, 210, 106, 150),
+            check:           Color::from_int_rgba(255, 0, 0, 220),
+            margin_text:     Color::WHITE,
+            panel_background: Color::from_int_rgb(40, 40, 40),
+            panel_text:      Color::from_int_rgb(230, 230, 230),
+            current_move:    Color::from_int_rgb(70, 110, 70),
+            history:         Color::from_int_rgb(240, 170, 30),
+            clock_running:   Color::from_int_rgb(70, 110, 70),
+        }
+    }
+}
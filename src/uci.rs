@@ -0,0 +1,298 @@
+use std::io::{self, BufRead};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::chess::{Chess, Move, PieceColor, STARTING_FEN};
+use crate::engine::{self, EngineWorker, EngineReport, SearchLimits, SearchResult};
+
+// Time kept in hand on every move for the GUI and the operating system, unless set with setoption
+const DEFAULT_MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+// Lines from the GUI and reports from the engine, handled one at a time in the order they arrive
+enum Input {
+    Command(String),
+    Engine(EngineReport),
+}
+
+// State of a session with a GUI
+struct UciSession {
+    engine: EngineWorker,
+    // The position the next search starts from
+    chess: Chess,
+    // The search whose best move the GUI is waiting for
+    search: Option<u64>,
+    // go infinite must not answer before stop, even if the search ends by itself
+    infinite: bool,
+    // Best move of an infinite search that ended before stop came
+    pending_result: Option<SearchResult>,
+    move_overhead: Duration,
+}
+
+// chess --uci
+// Talks the Universal Chess Interface over stdin and stdout, so that the engine can be used from chess GUIs.
+pub fn run() {
+    let (sender, inputs) = mpsc::channel();
+
+    let engine_sender = sender.clone();
+    let engine = EngineWorker::spawn(move |report| {
+        let _ = engine_sender.send(Input::Engine(report));
+    });
+
+    // Reading stdin blocks, so it gets a thread of its own and the main thread can also listen to the engine
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break; };
+            if sender.send(Input::Command(line)).is_err() { return; }
+        }
+
+        // The GUI went away
+        let _ = sender.send(Input::Command("quit".to_string()));
+    });
+
+    let mut session = UciSession {
+        engine,
+        chess: Chess::new(STARTING_FEN).expect("Starting position FEN is valid"),
+        search: None,
+        infinite: false,
+        pending_result: None,
+        move_overhead: DEFAULT_MOVE_OVERHEAD,
+    };
+
+    for input in inputs.iter() {
+        match input {
+            Input::Command(line) => {
+                if !session.handle_command(&line) { break; }
+            },
+            Input::Engine(report) => session.handle_report(report),
+        };
+    }
+}
+
+impl UciSession {
+    // Returns false once the GUI has asked to quit
+    fn handle_command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = words.split_first() else { return true; };
+
+        match command {
+            "uci" => {
+                println!("id name Chess-rs {}", env!("CARGO_PKG_VERSION"));
+                println!("id author Chess-rs contributors");
+                println!("option name Move Overhead type spin default {} min 0 max 5000", DEFAULT_MOVE_OVERHEAD.as_millis());
+                println!("uciok");
+            },
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.stop();
+                self.chess = Chess::new(STARTING_FEN).expect("Starting position FEN is valid");
+            },
+            "position" => match parse_position(args) {
+                Ok(chess) => self.chess = chess,
+                Err(err)  => println!("info string {err}"),
+            },
+            "go" => {
+                let (limits, infinite) = parse_go(args, self.chess.get_turn(), self.move_overhead);
+                self.infinite = infinite;
+                self.pending_result = None;
+                self.search = Some(self.engine.search(self.chess.clone(), limits));
+            },
+            "stop" => self.stop(),
+            "setoption" => self.set_option(args),
+            "quit" => return false,
+            // ponderhit, debug and register are accepted but do nothing
+            "ponderhit" | "debug" | "register" => {},
+            _ => println!("info string unknown command {command}"),
+        };
+
+        true
+    }
+
+    fn handle_report(&mut self, report: EngineReport) {
+        match report {
+            EngineReport::Progress { search_id, result } if Some(search_id) == self.search => {
+                println!("{}", format_info(&result));
+            },
+            EngineReport::BestMove { search_id, result } if Some(search_id) == self.search => {
+                match self.infinite {
+                    true  => self.pending_result = Some(result),
+                    false => self.send_best_move(&result),
+                };
+            },
+            // Reports of searches the GUI is no longer waiting for
+            _ => {},
+        };
+    }
+
+    // Ends the search, which then answers with its best move
+    fn stop(&mut self) {
+        self.infinite = false;
+
+        match self.pending_result.take() {
+            Some(result) => self.send_best_move(&result),
+            None         => self.engine.stop(),
+        };
+    }
+
+    fn send_best_move(&mut self, result: &SearchResult) {
+        self.search = None;
+
+        match (result.best_move, result.pv.get(1)) {
+            (Some(best_move), Some(ponder)) => println!("bestmove {best_move} ponder {ponder}"),
+            (Some(best_move), None)         => println!("bestmove {best_move}"),
+            // No legal moves, the game is over
+            (None, _)                       => println!("bestmove 0000"),
+        };
+    }
+
+    // setoption name <name> [value <value>]. Option names may contain spaces.
+    fn set_option(&mut self, args: &[&str]) {
+        let value_index = args.iter().position(|&word| word == "value").unwrap_or(args.len());
+        let name  = args.get(1..value_index).unwrap_or_default().join(" ");
+        let value = args.get(value_index + 1..).unwrap_or_default().join(" ");
+
+        match name.to_ascii_lowercase().as_str() {
+            "move overhead" => match value.parse::<u64>() {
+                Ok(millis) => self.move_overhead = Duration::from_millis(millis.min(5000)),
+                Err(_)     => println!("info string invalid Move Overhead {value}"),
+            },
+            _ => println!("info string unknown option {name}"),
+        };
+    }
+}
+
+// position startpos [moves <move>...] or position fen <fen> [moves <move>...], with moves in coordinate notation
+fn parse_position(args: &[&str]) -> Result<Chess, String> {
+    let moves_index = args.iter().position(|&word| word == "moves").unwrap_or(args.len());
+    let (setup, moves) = args.split_at(moves_index);
+
+    let mut chess = match setup {
+        ["startpos"]      => Chess::new(STARTING_FEN).expect("Starting position FEN is valid"),
+        ["fen", fen @ ..] => Chess::new(&fen.join(" ")).map_err(|err| format!("invalid FEN: {err}"))?,
+        _                 => return Err("expected position startpos or position fen <fen>".to_string()),
+    };
+
+    for text in moves.iter().skip(1) {
+        let mv = parse_move(&chess, text).ok_or_else(|| format!("illegal move {text}"))?;
+        chess.make_move(mv);
+    }
+
+    chess.update_status();
+
+    Ok(chess)
+}
+
+// The legal move written as text, e.g. e2e4 or e7e8q
+fn parse_move(chess: &Chess, text: &str) -> Option<Move> {
+    chess.legal_moves().into_iter().find(|mv| mv.to_string() == text)
+}
+
+// go [depth <plies>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [movestogo <moves>] [infinite]
+// Returns the limits of the search and whether it is infinite. Anything else, such as ponder, is ignored.
+fn parse_go(args: &[&str], turn: PieceColor, move_overhead: Duration) -> (SearchLimits, bool) {
+    let mut limits = SearchLimits::default();
+    let mut infinite = false;
+    let mut time_left  = None;
+    let mut increment  = Duration::ZERO;
+    let mut moves_to_go = None;
+
+    let millis = |value: Option<&&str>| value.and_then(|value| value.parse::<u64>().ok()).map(Duration::from_millis);
+
+    let mut words = args.iter();
+    while let Some(&word) = words.next() {
+        match (word, turn) {
+            ("infinite", _)                => infinite = true,
+            ("depth", _)                   => limits.depth = words.next().and_then(|value| value.parse().ok()),
+            ("movetime", _)                => limits.movetime = millis(words.next()),
+            ("movestogo", _)               => moves_to_go = words.next().and_then(|value| value.parse().ok()),
+            ("wtime", PieceColor::WHITE) | ("btime", PieceColor::BLACK) => time_left = millis(words.next()),
+            ("winc", PieceColor::WHITE)  | ("binc", PieceColor::BLACK)  => increment = millis(words.next()).unwrap_or_default(),
+            // The opponent's clock
+            ("wtime" | "btime" | "winc" | "binc", _) => { words.next(); },
+            _ => {},
+        };
+    }
+
+    if infinite {
+        return (SearchLimits::default(), true);
+    }
+
+    if let (None, Some(time_left)) = (limits.movetime, time_left) {
+        limits.movetime = Some(engine::time_for_move(time_left, increment, moves_to_go, move_overhead));
+    }
+
+    (limits, false)
+}
+
+// info depth 5 score cp 35 nodes 12000 nps 80000 time 150 pv e2e4 e7e5
+fn format_info(result: &SearchResult) -> String {
+    let score = match result.mate_in() {
+        Some(moves) => format!("mate {moves}"),
+        None        => format!("cp {}", result.score),
+    };
+
+    // Counted per millisecond so a very quick search does not divide by zero
+    let millis = result.time.as_millis().max(1) as u64;
+    let nps = result.nodes * 1000 / millis;
+
+    let pv: Vec<String> = result.pv.iter().map(Move::to_string).collect();
+
+    format!("info depth {} score {score} nodes {} nps {nps} time {} pv {}", result.depth, result.nodes, result.time.as_millis(), pv.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{format_info, parse_go, parse_position};
+    use crate::chess::{Chess, GameStatus, PieceColor};
+    use crate::engine::SearchResult;
+
+    fn words(text: &str) -> Vec<&str> {
+        text.split_whitespace().collect()
+    }
+
+    #[test]
+    fn sets_up_positions_with_moves() {
+        let chess = parse_position(&words("startpos moves e2e4 c7c5 g1f3")).unwrap();
+        assert_eq!(chess.to_fen(), "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+
+        let chess = parse_position(&words("fen 4k3/1P6/8/8/8/8/8/4K2R w K - 0 1 moves e1g1 e8d7 b7b8n")).unwrap();
+        assert_eq!(chess.to_fen(), "1N6/3k4/8/8/8/8/8/5RK1 b - - 0 2");
+
+        // The game ends just like it does when the moves are played on the board
+        let chess = parse_position(&words("startpos moves f2f3 e7e5 g2g4 d8h4")).unwrap();
+        assert_eq!(chess.get_status(), GameStatus::Checkmate(PieceColor::BLACK));
+
+        assert_eq!(parse_position(&words("startpos moves e2e5")).err(), Some("illegal move e2e5".to_string()));
+        assert!(parse_position(&words("fen 8/8 w - - 0 1")).is_err());
+        assert!(parse_position(&words("moves e2e4")).is_err());
+    }
+
+    #[test]
+    fn reads_search_limits() {
+        let overhead = Duration::from_millis(30);
+
+        let (limits, infinite) = parse_go(&words("depth 6 movetime 500"), PieceColor::WHITE, overhead);
+        assert_eq!((limits.depth, limits.movetime, infinite), (Some(6), Some(Duration::from_millis(500)), false));
+
+        // Only the clock of the side to move counts
+        let (limits, _) = parse_go(&words("wtime 1000 btime 60000 winc 0 binc 2000"), PieceColor::BLACK, overhead);
+        assert_eq!(limits.movetime, Some(Duration::from_millis(2000 + 1500)));
+
+        let (limits, infinite) = parse_go(&words("infinite wtime 1000"), PieceColor::WHITE, overhead);
+        assert_eq!((limits.depth, limits.movetime, infinite), (None, None, true));
+    }
+
+    #[test]
+    fn formats_info_lines() {
+        let chess = Chess::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mate = chess.legal_moves().into_iter().find(|mv| mv.to_string() == "a1a8").unwrap();
+
+        let mut result = SearchResult { best_move: Some(mate), score: 40, depth: 3, pv: vec![mate], nodes: 5000, time: Duration::from_millis(250) };
+        assert_eq!(format_info(&result), "info depth 3 score cp 40 nodes 5000 nps 20000 time 250 pv a1a8");
+
+        result.score = -99_996;
+        assert!(format_info(&result).contains("score mate -2 "));
+    }
+}
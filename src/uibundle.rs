@@ -2,34 +2,62 @@ use speedy2d::shape::URect;
 use speedy2d::dimen::{UVec2, Vector2};
 use speedy2d::Graphics2D;
 
+use std::time::Duration;
 
-use crate::userinputhandler::UserInputHandler;
-use crate::renderer::Renderer;
-use crate::chess::Chess;
+use crate::userinputhandler::{UserInputHandler, PROMOTION_CHOICES};
+use crate::renderer::{Renderer, CoordinatePlacement};
+use crate::movelist::MoveList;
+use crate::chess::{Chess, PieceColor, GameStatus};
 
 pub struct UIBundle {
     window_rect:   URect,
     gameview_rect: URect,
+    // Side panel to the right of the board with the move list
+    panel_rect:    URect,
+    // Width the panel gets when the window is wide enough, it shrinks to fit otherwise
+    panel_width:   u32,
+    // Gaps around the board and panel that resizing keeps, as long as the window is big enough
+    margin_x:      u32,
+    margin_y:      u32,
+    move_list:     MoveList,
     renderer:      Renderer,
     input_handler: UserInputHandler,
     loaded_images: bool,
+    // Whether the legal moves of the selected piece are marked on the board
+    show_move_hints: bool,
+    // Whether the board turns around after every move to put the side to move at the bottom, for pass-and-play
+    auto_flip: bool,
+    coordinate_placement: CoordinatePlacement,
 }
 
 impl UIBundle {
-    pub fn new(window_width: u32, window_height: u32, gameview_width: u32, gameview_height: u32) -> Self {
+    pub fn new(window_width: u32, window_height: u32, gameview_width: u32, gameview_height: u32, panel_width: u32) -> Self {
 
-        let (window_rect, gameview_rect) = Self::build_rects(window_width, window_height, gameview_width, gameview_height);
+        let (window_rect, gameview_rect, panel_rect) = Self::build_rects(window_width, window_height, gameview_width, gameview_height, panel_width);
+        let margin_x = gameview_rect.top_left().x;
+        let margin_y = gameview_rect.top_left().y;
 
         UIBundle {
             window_rect,
             gameview_rect,
+            panel_rect,
+            panel_width,
+            margin_x,
+            margin_y,
+            move_list:      MoveList::new(),
             renderer:       Renderer::new(),
             input_handler:  UserInputHandler::new(),
             loaded_images:  false,
+            show_move_hints: true,
+            auto_flip:      false,
+            coordinate_placement: CoordinatePlacement::Margin,
         }
     }
 
-    fn build_rects(window_width: u32, window_height: u32, gameview_width: u32, gameview_height: u32) -> (URect, URect) {
+    // The board and the side panel share the window's width, with equal gaps left of the board,
+    // between the board and the panel, and right of the panel.
+    // The panel gets whatever width is left next to the board, up to panel_width.
+    fn build_rects(window_width: u32, window_height: u32, gameview_width: u32, gameview_height: u32, panel_width: u32) -> (URect, URect, URect) {
    
         let window_rect = {
             // Build main window rectangle
@@ -39,13 +67,15 @@ impl UIBundle {
             URect::from_tuples(window_topleft, window_bottomright)
         };
 
+        let panel_width = panel_width.min(window_width.saturating_sub(gameview_width));
+
         // Build gameview rectangle
 
         let gameview_rect = {
             // Top left
             let game_topleft = {
-                let game_topleft_x = (window_width  - gameview_width)  / 2;
-                let game_topleft_y = (window_height - gameview_height) / 2;
+                let game_topleft_x = window_width.saturating_sub(gameview_width + panel_width) / 3;
+                let game_topleft_y = window_height.saturating_sub(gameview_height) / 2;
     
                 (game_topleft_x, game_topleft_y)
             };
@@ -65,29 +95,74 @@ impl UIBundle {
             URect::from_tuples(game_topleft, game_bottomright)
         };
 
+        // Build panel rectangle, as tall as the board
+        let panel_rect = {
+            let panel_topleft     = (gameview_rect.bottom_right().x + gameview_rect.top_left().x, gameview_rect.top_left().y);
+            let panel_bottomright = (panel_topleft.0 + panel_width, gameview_rect.bottom_right().y);
+
+            assert!(panel_bottomright.0 <= window_width);
+
+            URect::from_tuples(panel_topleft, panel_bottomright)
+        };
+
         // Return rectangles as tuple
-        (window_rect, gameview_rect)
+        (window_rect, gameview_rect, panel_rect)
     }
 
+    // The board stays square: it takes the largest size that fits both the width and the height left over by the margins.
     pub fn resize_window(&mut self, window_dimensions: &UVec2) {
-        let buffer_size_x = self.gameview_rect.top_left().x;
-        let buffer_size_y = self.gameview_rect.top_left().y;
+        let window_width  = window_dimensions.x;
+        let window_height = window_dimensions.y;
 
-        let window_width    = window_dimensions.x;
-        let window_height   = window_dimensions.y;
-        let gameview_width  = window_width  - (buffer_size_x * 2);
-        let gameview_height = window_height - (buffer_size_y * 2);
+        let available_width  = window_width.saturating_sub(self.margin_x * 3 + self.panel_width);
+        let available_height = window_height.saturating_sub(self.margin_y * 2);
+        let board_length     = available_width.min(available_height);
 
-        (self.window_rect, self.gameview_rect) = Self::build_rects(window_width, window_height, gameview_width, gameview_height);
-    
+        (self.window_rect, self.gameview_rect, self.panel_rect) = Self::build_rects(window_width, window_height, board_length, board_length, self.panel_width);
     }
 
     pub fn set_hovered_square(&mut self, pos: &Vector2<f32>) {
-        self.input_handler.set_hovered_square(&self.window_rect, &self.gameview_rect, pos);
+        self.input_handler.set_hovered_square(&self.window_rect, &self.gameview_rect, self.renderer.get_orientation(), pos);
+    }
+
+    // A click on the move list shows the position after that move. While an earlier position is shown, or while
+    // board_enabled is false, the board is read-only.
+    pub fn mouse_pressed(&mut self, chess: &mut Chess, board_enabled: bool) {
+        let mouse_position = self.input_handler.get_mouse_position();
+
+        if let Some(index) = self.move_list.index_at_position(&self.panel_rect, self.move_list_focus(), mouse_position) {
+            self.input_handler.view_ply(index + 1, self.move_list.len());
+        } else if board_enabled && self.input_handler.get_viewed_ply().is_none() {
+            self.input_handler.mouse_pressed(chess);
+        }
+    }
+
+    pub fn mouse_released(&mut self, chess: &mut Chess) {
+        self.input_handler.mouse_released(chess);
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.input_handler.clear_selection();
+    }
+
+    pub fn open_move_prompt(&mut self) {
+        self.input_handler.open_move_prompt();
+    }
+
+    pub fn close_move_prompt(&mut self) {
+        self.input_handler.close_move_prompt();
+    }
+
+    pub fn type_char(&mut self, ch: char) {
+        self.input_handler.type_char(ch);
+    }
+
+    pub fn erase_char(&mut self) {
+        self.input_handler.erase_char();
     }
 
-    pub fn mouse_clicked(&mut self, chess: &mut Chess) {
-        self.input_handler.mouse_clicked(chess);
+    pub fn submit_typed_move(&mut self, chess: &mut Chess) {
+        self.input_handler.submit_typed_move(chess);
     }
 
     pub fn load_images(&mut self, graphics: &mut Graphics2D) {
@@ -97,16 +172,139 @@ impl UIBundle {
         }
     }
 
+    // The dragged piece is left out, it is drawn under the mouse by draw_dragged_piece() instead
     pub fn draw_chessboard(&self, chess: &Chess, graphics: &mut Graphics2D) {
-        self.renderer.draw_chessboard(&self.gameview_rect, chess, graphics);
+        self.renderer.draw_chessboard(&self.gameview_rect, chess, self.input_handler.get_dragged_piece_square(), graphics);
+    }
+
+    pub fn draw_dragged_piece(&self, chess: &Chess, graphics: &mut Graphics2D) {
+        if let Some(square) = self.input_handler.get_dragged_piece_square() {
+            let piece = chess.get_piece_at_square(square).unwrap();
+            self.renderer.draw_dragged_piece(&self.gameview_rect, piece, self.input_handler.get_mouse_position(), graphics);
+        }
     }
 
     pub fn draw_hovered_square(&self, hovered_square: usize, graphics: &mut Graphics2D) {
-        Renderer::draw_hovered_square(&self.gameview_rect, hovered_square, graphics);
+        self.renderer.draw_hovered_square(&self.gameview_rect, hovered_square, graphics);
+    }
+
+    pub fn flip_board(&mut self) {
+        self.set_orientation(self.renderer.get_orientation().opposite());
+    }
+
+    // The square under the mouse changes along with the board, even if the mouse does not move.
+    pub fn set_orientation(&mut self, orientation: PieceColor) {
+        self.renderer.set_orientation(orientation);
+
+        let mouse_position = self.input_handler.get_mouse_position();
+        self.set_hovered_square(&mouse_position);
+    }
+
+    pub fn is_auto_flip(&self) -> bool {
+        self.auto_flip
+    }
+
+    pub fn set_auto_flip(&mut self, auto_flip: bool) {
+        self.auto_flip = auto_flip;
+    }
+
+    // With auto-flip on, turns the board so that the side to move is at the bottom.
+    pub fn follow_turn(&mut self, chess: &Chess) {
+        if self.auto_flip && self.renderer.get_orientation() != chess.get_turn() {
+            self.set_orientation(chess.get_turn());
+        }
+    }
+
+    pub fn cycle_coordinate_placement(&mut self) -> CoordinatePlacement {
+        self.coordinate_placement = self.coordinate_placement.next();
+        self.coordinate_placement
+    }
+
+    pub fn update_move_list(&mut self, chess: &Chess) {
+        self.move_list.update(chess);
+    }
+
+    // The move highlighted in the list: the one leading to the position shown
+    fn move_list_focus(&self) -> Option<usize> {
+        match self.input_handler.get_viewed_ply() {
+            Some(ply) => ply.checked_sub(1),
+            None      => self.move_list.len().checked_sub(1),
+        }
+    }
+
+    pub fn draw_move_list(&self, graphics: &mut Graphics2D) {
+        let viewing_history = self.input_handler.get_viewed_ply().is_some();
+        self.renderer.draw_move_list(&self.panel_rect, &self.move_list, self.move_list_focus(), viewing_history, graphics);
+    }
+
+    pub fn draw_history_frame(&self, graphics: &mut Graphics2D) {
+        self.renderer.draw_history_frame(&self.gameview_rect, graphics);
+    }
+
+    // Number of moves into the game of the earlier position being shown, or None when showing the game as it stands
+    pub fn get_viewed_ply(&self) -> Option<usize> {
+        self.input_handler.get_viewed_ply()
+    }
+
+    pub fn view_previous_move(&mut self) {
+        let ply = self.input_handler.get_viewed_ply().unwrap_or(self.move_list.len());
+        self.input_handler.view_ply(ply.saturating_sub(1), self.move_list.len());
+    }
+
+    pub fn view_next_move(&mut self) {
+        if let Some(ply) = self.input_handler.get_viewed_ply() {
+            self.input_handler.view_ply(ply + 1, self.move_list.len());
+        }
+    }
+
+    pub fn view_first_move(&mut self) {
+        self.input_handler.view_ply(0, self.move_list.len());
+    }
+
+    pub fn show_live_position(&mut self) {
+        self.input_handler.show_live_position();
+    }
+
+    pub fn draw_coordinates(&self, graphics: &mut Graphics2D) {
+        self.renderer.draw_coordinates(&self.window_rect, &self.gameview_rect, self.coordinate_placement, graphics);
+    }
+
+    pub fn toggle_move_hints(&mut self) -> bool {
+        self.show_move_hints = !self.show_move_hints;
+        self.show_move_hints
+    }
+
+    pub fn draw_move_hints(&self, chess: &Chess, graphics: &mut Graphics2D) {
+        if !self.show_move_hints { return; }
+
+        if let Some(selected_square) = self.input_handler.get_selected_piece_square() {
+            // A promoting pawn has one move per piece it can promote to, but each square only needs marking once
+            let mut destinations: Vec<usize> = chess.legal_moves_from(selected_square).iter().map(|mv| mv.dst).collect();
+            destinations.dedup();
+
+            self.renderer.draw_move_hints(&self.gameview_rect, &destinations, chess, graphics);
+        }
     }
 
     pub fn draw_selected_piece_square(&self, selected_piece_square: usize, graphics: &mut Graphics2D) {
-        Renderer::draw_selected_piece_square(&self.gameview_rect, selected_piece_square, graphics);
+        self.renderer.draw_selected_piece_square(&self.gameview_rect, selected_piece_square, graphics);
+    }
+
+    pub fn draw_promotion_picker(&self, promotion_square: usize, color: PieceColor, graphics: &mut Graphics2D) {
+        let squares = UserInputHandler::promotion_picker_squares(promotion_square);
+        self.renderer.draw_promotion_picker(&self.gameview_rect, &squares, &PROMOTION_CHOICES, color, graphics);
+    }
+
+    pub fn draw_game_over_overlay(&self, status: GameStatus, graphics: &mut Graphics2D) {
+        self.renderer.draw_game_over_overlay(&self.gameview_rect, status, graphics);
+    }
+
+    pub fn draw_move_prompt(&self, typed_move: &str, graphics: &mut Graphics2D) {
+        self.renderer.draw_move_prompt(&self.window_rect, &self.gameview_rect, typed_move, graphics);
+    }
+
+    pub fn draw_clock(&self, color: PieceColor, time: Duration, running: bool, graphics: &mut Graphics2D) {
+        self.renderer.draw_clock(&self.window_rect, &self.panel_rect, color, time, running, graphics);
     }
 
     pub fn get_hovered_square(&self) -> Option<usize> {
@@ -117,6 +315,35 @@ impl UIBundle {
         self.input_handler.get_selected_piece_square()
     }
 
+    pub fn get_pending_promotion(&self) -> Option<(usize, usize)> {
+        self.input_handler.get_pending_promotion()
+    }
+
+    pub fn get_typed_move(&self) -> Option<&str> {
+        self.input_handler.get_typed_move()
+    }
+
 }
 
+#[cfg(test)]
+mod tests {
+    use speedy2d::dimen::UVec2;
 
+    use super::UIBundle;
+
+    #[test]
+    fn layout_survives_resizing_to_nothing_and_back() {
+        let mut ui = UIBundle::new(1050, 800, 750, 750, 225);
+        let initial = (ui.window_rect.clone(), ui.gameview_rect.clone(), ui.panel_rect.clone());
+
+        ui.resize_window(&UVec2::new(0, 0));
+        ui.resize_window(&UVec2::new(1050, 800));
+        assert_eq!((ui.window_rect.clone(), ui.gameview_rect.clone(), ui.panel_rect.clone()), initial);
+
+        // A window taller than it is wide still gets a square board
+        ui.resize_window(&UVec2::new(1050, 1200));
+        let board = &ui.gameview_rect;
+        assert_eq!(board.bottom_right().x - board.top_left().x, 750);
+        assert_eq!(board.bottom_right().y - board.top_left().y, 750);
+    }
+}
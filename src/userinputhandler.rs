@@ -1,11 +1,24 @@
 use speedy2d::shape::URect;
 use speedy2d::dimen::Vector2;
 
-use crate::chess::Chess;
+use crate::chess::{Chess, PieceColor, PieceName};
+use crate::renderer::Renderer;
+
+// Order of the pieces in the promotion picker, starting on the promotion square and heading towards the board's center.
+pub const PROMOTION_CHOICES: [PieceName; 4] = [PieceName::QUEEN, PieceName::ROOK, PieceName::BISHOP, PieceName::KNIGHT];
 
 pub struct UserInputHandler {
     hovered_square:  Option<usize>,
     selected_piece_square: Option<usize>,
+    // (src, dst) of a promoting move waiting for the player to pick a piece
+    pending_promotion: Option<(usize, usize)>,
+    // The move being typed in SAN while the move prompt is open
+    typed_move: Option<String>,
+    // Square of the piece held under the mouse while it is dragged
+    dragged_piece_square: Option<usize>,
+    mouse_position: Vector2<f32>,
+    // Number of moves into the game of the earlier position being looked at, None while playing
+    viewed_ply: Option<usize>,
 }
 
 impl UserInputHandler {
@@ -13,23 +26,129 @@ impl UserInputHandler {
         UserInputHandler {
             hovered_square: None,
             selected_piece_square: None,
+            pending_promotion: None,
+            typed_move: None,
+            dragged_piece_square: None,
+            mouse_position: Vector2::ZERO,
+            viewed_ply: None,
         }
     }
 
-    pub fn mouse_clicked(&mut self, chess: &mut Chess) {
-        if self.hovered_square.is_some() {
+    // Pressing on a piece of the side to move picks it up, pressing anywhere else is a click.
+    pub fn mouse_pressed(&mut self, chess: &mut Chess) {
+        let Some(square) = self.hovered_square else { return };
+
+        let is_own_piece = chess.get_piece_at_square(square).is_some_and(|piece| chess.is_turn_for_piece(piece));
+
+        if is_own_piece && self.pending_promotion.is_none() {
+            self.selected_piece_square = Some(square);
+            self.dragged_piece_square  = Some(square);
+        } else {
             self.square_clicked(chess);
         }
     }
 
+    // Dropping a dragged piece on another square moves it there, the same as clicking that square.
+    // Dropped on its own square it stays selected, so click-click still works.
+    // Dropped off the board, or where the move is not legal, it goes back to where it came from.
+    pub fn mouse_released(&mut self, chess: &mut Chess) {
+        let Some(src) = self.dragged_piece_square.take() else { return };
+
+        match self.hovered_square {
+            Some(dst) if dst == src => {},
+            Some(_)                 => self.square_clicked(chess),
+            None                    => self.selected_piece_square = None,
+        };
+    }
+
+    // Drops the selected piece and any open promotion picker, e.g. after the position changed underneath them.
+    pub fn clear_selection(&mut self) {
+        self.selected_piece_square = None;
+        self.pending_promotion = None;
+        self.dragged_piece_square = None;
+    }
+
+    // Looks at the position ply moves into the game, or goes back to playing once ply reaches the live position.
+    pub fn view_ply(&mut self, ply: usize, live_ply: usize) {
+        self.clear_selection();
+
+        self.viewed_ply = match ply < live_ply {
+            true  => Some(ply),
+            false => None,
+        };
+    }
+
+    pub fn show_live_position(&mut self) {
+        self.viewed_ply = None;
+    }
+
+    pub fn get_viewed_ply(&self) -> Option<usize> {
+        self.viewed_ply
+    }
+
+    pub fn open_move_prompt(&mut self) {
+        self.typed_move = Some(String::new());
+    }
+
+    pub fn close_move_prompt(&mut self) {
+        self.typed_move = None;
+    }
+
+    pub fn type_char(&mut self, ch: char) {
+        if let Some(typed_move) = &mut self.typed_move {
+            typed_move.push(ch);
+        }
+    }
+
+    pub fn erase_char(&mut self) {
+        if let Some(typed_move) = &mut self.typed_move {
+            typed_move.pop();
+        }
+    }
+
+    // Plays the typed move and closes the prompt. A move that cannot be played is left in the prompt to be corrected.
+    pub fn submit_typed_move(&mut self, chess: &mut Chess) {
+        let Some(typed_move) = &self.typed_move else { return };
+
+        match chess.parse_san(typed_move) {
+            Ok(mv) => {
+                chess.attempt_move(mv.src, mv.dst, mv.promotion);
+                self.typed_move = None;
+                self.clear_selection();
+            },
+            Err(err) => log::warn!("Cannot play {typed_move}: {err}"),
+        };
+    }
+
     fn square_clicked(&mut self, chess: &mut Chess) {
         assert!(self.hovered_square.is_some());
 
-        if let Some(selected_square) = self.selected_piece_square {
+        if let Some((src, dst)) = self.pending_promotion {
+            // The picker is open: clicking one of its pieces promotes, clicking anywhere else cancels the move.
+            let clicked_square = self.hovered_square.unwrap();
+            let picked = Self::promotion_picker_squares(dst)
+                .iter()
+                .position(|&square| square == clicked_square)
+                .map(|index| PROMOTION_CHOICES[index]);
+
+            if picked.is_some() {
+                chess.attempt_move(src, dst, picked);
+            }
+
+            self.pending_promotion = None;
+            self.selected_piece_square = None;
+
+        } else if let Some(selected_square) = self.selected_piece_square {
             let src = selected_square;
             let dst = self.hovered_square.unwrap();
 
-            chess.attempt_move(src, dst);
+            // Promotions need a piece choice first, so open the picker instead of moving.
+            if chess.is_promotion_move(src, dst) {
+                self.pending_promotion = Some((src, dst));
+                return;
+            }
+
+            chess.attempt_move(src, dst, None);
             self.selected_piece_square = None;
         
         } else {
@@ -67,7 +186,9 @@ impl UserInputHandler {
 
     }
 
-    pub fn set_hovered_square(&mut self, window_rect: &URect, gameview_rect: &URect, mouse_position: &Vector2<f32>) {
+    pub fn set_hovered_square(&mut self, window_rect: &URect, gameview_rect: &URect, orientation: PieceColor, mouse_position: &Vector2<f32>) {
+        self.mouse_position = *mouse_position;
+
         let out_of_bounds_y = mouse_position.y < gameview_rect.top_left().y as f32  ||  mouse_position.y > gameview_rect.bottom_right().y as f32;
         let out_of_bounds_x = mouse_position.x < gameview_rect.top_left().x as f32  ||  mouse_position.x > gameview_rect.bottom_right().x as f32;
 
@@ -91,7 +212,7 @@ impl UserInputHandler {
 
             let x_offset = (gameview_rect.top_left().x - window_rect.top_left().x) as f32;
 
-            ((mouse_position.x - x_offset) / ratio_x as f32) as u32
+            ((mouse_position.x - x_offset) / ratio_x) as u32
         };
 
         let board_pos_y = {
@@ -103,12 +224,13 @@ impl UserInputHandler {
 
             let y_offset = (gameview_rect.top_left().y - window_rect.top_left().y) as f32;
 
-            ((mouse_position.y - y_offset) / ratio_y as f32) as u32
+            ((mouse_position.y - y_offset) / ratio_y) as u32
         };
 
 
-        let square = (board_pos_y * 8 + board_pos_x) as usize;
-        self.hovered_square = Some( square );
+        // The bottom and right edges belong to the last row and column
+        let display_square = (board_pos_y.min(7) * 8 + board_pos_x.min(7)) as usize;
+        self.hovered_square = Some( Renderer::display_square(display_square, orientation) );
 
     }
 
@@ -120,4 +242,30 @@ impl UserInputHandler {
         self.selected_piece_square
     }
 
+    pub fn get_pending_promotion(&self) -> Option<(usize, usize)> {
+        self.pending_promotion
+    }
+
+    pub fn get_dragged_piece_square(&self) -> Option<usize> {
+        self.dragged_piece_square
+    }
+
+    pub fn get_mouse_position(&self) -> Vector2<f32> {
+        self.mouse_position
+    }
+
+    pub fn get_typed_move(&self) -> Option<&str> {
+        self.typed_move.as_deref()
+    }
+
+    // The four squares of the promotion picker, laid out along the promotion square's file towards the center.
+    pub fn promotion_picker_squares(promotion_square: usize) -> [usize; 4] {
+        match promotion_square / 8 {
+            // White promotes on the top rank, so the picker extends downwards
+            0 => [promotion_square, promotion_square + 8, promotion_square + 16, promotion_square + 24],
+            // Black promotes on the bottom rank, so the picker extends upwards
+            _ => [promotion_square, promotion_square - 8, promotion_square - 16, promotion_square - 24],
+        }
+    }
+
 }
\ No newline at end of file